@@ -0,0 +1,32 @@
+use candles_shared::caches::candle_prices_cache::CandlePricesCache;
+use candles_shared::models::{candle_data::CandleData, candle_type::CandleType};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+fn get_by_date_range(c: &mut Criterion) {
+    let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+    let mut group = c.benchmark_group("get_by_date_range");
+
+    for size in [100, 1000, 10000] {
+        let mut cache = CandlePricesCache::new(CandleType::Minute);
+
+        for i in 0..size {
+            cache.init(CandleData::new(from + Duration::minutes(i), 100.0 + i as f64, 1.0));
+        }
+
+        let date_from = from + Duration::minutes(size / 4);
+        let date_to = date_from + Duration::minutes(size / 2);
+
+        group.bench_with_input(BenchmarkId::new("half", size), &cache, |b, cache| {
+            b.iter(|| cache.get_by_date_range(black_box(date_from), black_box(date_to)))
+        });
+        group.bench_with_input(BenchmarkId::new("single", size), &cache, |b, cache| {
+            b.iter(|| cache.get_by_date_range(black_box(date_from), black_box(date_from + Duration::minutes(1))))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, get_by_date_range);
+criterion_main!(benches);
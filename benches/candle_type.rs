@@ -0,0 +1,15 @@
+use candles_shared::models::candle_type::CandleType;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+fn get_start_dates(c: &mut Criterion) {
+    let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+    let to = from + Duration::days(30);
+
+    c.bench_function("get_start_dates 30 days of minutes", |b| {
+        b.iter(|| CandleType::Minute.get_start_dates(black_box(from), black_box(to)))
+    });
+}
+
+criterion_group!(benches, get_start_dates);
+criterion_main!(benches);
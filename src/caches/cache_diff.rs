@@ -0,0 +1,14 @@
+/// Difference of two `CandlesCache` instances by candle ids, each list ordered by id
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheDiff {
+    pub only_self: Vec<String>,
+    pub only_other: Vec<String>,
+    /// Ids present in both caches with different candle data
+    pub changed: Vec<String>,
+}
+
+impl CacheDiff {
+    pub fn is_empty(&self) -> bool {
+        self.only_self.is_empty() && self.only_other.is_empty() && self.changed.is_empty()
+    }
+}
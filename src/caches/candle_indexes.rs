@@ -0,0 +1,117 @@
+use crate::models::{candle::BidAskCandle, candle_type::CandleType};
+use ahash::{AHashMap, AHashSet};
+use chrono::{DateTime, Utc};
+use compact_str::CompactString;
+use std::collections::BTreeMap;
+
+/// Secondary indexes of candle ids used by `CandlesCache`
+#[derive(Debug, Default, Clone)]
+pub(crate) struct CandleIndexes {
+    pub ids_by_instrument: AHashMap<CompactString, AHashSet<String>>,
+    pub ids_by_type: AHashMap<CandleType, AHashSet<String>>,
+    /// Ids of every instrument and candle type series ordered by candle period start used in the id
+    pub ids_by_series: AHashMap<(CompactString, CandleType), BTreeMap<DateTime<Utc>, String>>,
+}
+
+impl CandleIndexes {
+    pub fn add(&mut self, id: &str, candle: &BidAskCandle) {
+        self.ids_by_instrument
+            .entry(candle.instrument.clone())
+            .or_default()
+            .insert(id.to_owned());
+        self.ids_by_type
+            .entry(candle.candle_type.clone())
+            .or_default()
+            .insert(id.to_owned());
+        self.ids_by_series
+            .entry((candle.instrument.clone(), candle.candle_type.clone()))
+            .or_default()
+            .insert(get_period_start(candle), id.to_owned());
+    }
+
+    pub fn remove(&mut self, id: &str, candle: &BidAskCandle) {
+        if let Some(ids) = self.ids_by_instrument.get_mut(&candle.instrument) {
+            ids.remove(id);
+
+            if ids.is_empty() {
+                self.ids_by_instrument.remove(&candle.instrument);
+            }
+        }
+
+        if let Some(ids) = self.ids_by_type.get_mut(&candle.candle_type) {
+            ids.remove(id);
+
+            if ids.is_empty() {
+                self.ids_by_type.remove(&candle.candle_type);
+            }
+        }
+
+        let series_key = (candle.instrument.clone(), candle.candle_type.clone());
+
+        if let Some(ids) = self.ids_by_series.get_mut(&series_key) {
+            let period_start = get_period_start(candle);
+
+            if ids.get(&period_start).is_some_and(|indexed_id| indexed_id == id) {
+                ids.remove(&period_start);
+            }
+
+            if ids.is_empty() {
+                self.ids_by_series.remove(&series_key);
+            }
+        }
+    }
+
+    /// Gets id of the oldest candle which is not the newest one of its series.
+    /// Newest candles are still updated, so they are taken only if every series has a single candle
+    pub fn get_eviction_id(&self) -> Option<&String> {
+        let oldest_in_series = |is_finished: bool| {
+            self.ids_by_series
+                .iter()
+                .filter(|(_key, ids)| !is_finished || ids.len() > 1)
+                .filter_map(|(key, ids)| ids.first_key_value().map(|(datetime, id)| ((*datetime, key), id)))
+                .min_by(|(a, _), (b, _)| a.cmp(b))
+                .map(|(_, id)| id)
+        };
+
+        oldest_in_series(true).or_else(|| oldest_in_series(false))
+    }
+
+    /// Removes series entries of the id without a cached candle
+    pub fn remove_stale_id(&mut self, id: &str) {
+        self.ids_by_series.retain(|_key, ids| {
+            ids.retain(|_datetime, indexed_id| indexed_id != id);
+
+            !ids.is_empty()
+        });
+    }
+
+    /// Checks if any id is indexed for the instrument and candle type series
+    pub fn contains_series(&self, instrument: &str, candle_type: &CandleType) -> bool {
+        self.ids_by_series.contains_key(&(instrument.into(), candle_type.clone()))
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        for ids in self.ids_by_instrument.values_mut() {
+            ids.shrink_to_fit();
+        }
+
+        for ids in self.ids_by_type.values_mut() {
+            ids.shrink_to_fit();
+        }
+
+        self.ids_by_instrument.shrink_to_fit();
+        self.ids_by_type.shrink_to_fit();
+        self.ids_by_series.shrink_to_fit();
+    }
+
+    pub fn clear(&mut self) {
+        self.ids_by_instrument.clear();
+        self.ids_by_type.clear();
+        self.ids_by_series.clear();
+    }
+}
+
+// unaligned candles share the id of the aligned period
+fn get_period_start(candle: &BidAskCandle) -> DateTime<Utc> {
+    candle.candle_type.get_start_date(candle.datetime)
+}
@@ -1,16 +1,80 @@
+//! Candle prices of one candle type stored in a `BTreeMap` by period start timestamp.
+//!
+//! `get_by_date_range` finds the range start in O(log n) and clones every candle in range,
+//! so its cost is dominated by the result size rather than the cache size.
+//! Measure with `cargo bench --features bench --bench candle_prices_cache`
+
 use std::{collections::{BTreeMap}};
-use chrono::{DateTime, Utc};
-use crate::models::{candle_type::CandleType, candle_data::CandleData};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use crate::models::{candle_type::CandleType, candle_data::CandleData, candle_sdk_error::CandleSdkError, gap_fill_mode::GapFillMode};
 
 #[derive(Debug, Clone)]
 pub struct CandlePricesCache{
     pub candle_type: CandleType,
-    pub prices_by_date: BTreeMap<i64, CandleData>
+    pub prices_by_date: BTreeMap<i64, CandleData>,
+    /// Min count of candles required by indicators to produce values
+    pub min_periods: usize,
+    /// Shift of period boundaries from the candle type alignment set by `rebucket`
+    pub offset_secs: i64,
 }
 
 impl CandlePricesCache {
     pub fn new(candle_type: CandleType) -> Self{
-        Self { candle_type, prices_by_date: BTreeMap::new() }
+        Self { candle_type, prices_by_date: BTreeMap::new(), min_periods: 1, offset_secs: 0 }
+    }
+
+    /// Creates cache with empty candles for every period from `date_from` to `date_to` inclusive
+    pub fn seeded(candle_type: CandleType, date_from: DateTime<Utc>, date_to: DateTime<Utc>) -> Self {
+        let mut cache = Self::new(candle_type);
+
+        for date in cache.candle_type.get_start_dates(date_from, date_to) {
+            cache.prices_by_date.insert(date.timestamp(), CandleData::new_empty(date));
+        }
+
+        cache
+    }
+
+    pub fn new_with_min_periods(candle_type: CandleType, min_periods: usize) -> Self{
+        assert!(min_periods > 0, "min_periods must be positive");
+
+        Self { candle_type, prices_by_date: BTreeMap::new(), min_periods, offset_secs: 0 }
+    }
+
+    /// Gets start of the period containing specified date shifted by `offset_secs`
+    pub fn get_start_date(&self, datetime: DateTime<Utc>) -> DateTime<Utc> {
+        let offset = Duration::seconds(self.offset_secs);
+
+        self.candle_type.get_start_date(datetime - offset) + offset
+    }
+
+    pub fn len(&self) -> usize {
+        self.prices_by_date.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prices_by_date.is_empty()
+    }
+
+    pub fn has_sufficient_history(&self, min_periods: usize) -> bool {
+        self.len() >= min_periods
+    }
+
+    /// Gets simple moving average of close prices. Empty when history is shorter than period or min_periods
+    pub fn sma(&self, period: usize) -> Vec<(DateTime<Utc>, f64)> {
+        if period == 0 || !self.has_sufficient_history(period.max(self.min_periods)) {
+            return vec![];
+        }
+
+        let candles: Vec<(&i64, &CandleData)> = self.prices_by_date.iter().collect();
+
+        candles
+            .windows(period)
+            .map(|window| {
+                let sum: f64 = window.iter().map(|(_, candle)| candle.close).sum();
+
+                (to_datetime(*window[period - 1].0), sum / period as f64)
+            })
+            .collect()
     }
 
     pub fn init(&mut self, candle: CandleData){
@@ -18,7 +82,7 @@ impl CandlePricesCache {
     }
 
     pub fn update(&mut self, datetime: DateTime<Utc>, rate: f64, volume: f64){
-        let candle_date = self.candle_type.get_start_date(datetime);
+        let candle_date = self.get_start_date(datetime);
         let timestamp_sec = candle_date.timestamp();
         let target_candle = self.prices_by_date.get_mut(&timestamp_sec);
 
@@ -43,7 +107,1180 @@ impl CandlePricesCache {
         result
     }
 
+    /// Gets volume of candles in range `[date_from, date_to)` by price bucket index (`price / bucket_size`).
+    /// Candle volume is split equally between buckets from its low to its high
+    pub fn volume_profile(&self, date_from: DateTime<Utc>, date_to: DateTime<Utc>, bucket_size: f64) -> BTreeMap<i64, f64> {
+        let mut profile = BTreeMap::new();
+
+        if bucket_size <= 0.0 {
+            return profile;
+        }
+
+        for (_date, candle) in self.prices_by_date.range(date_from.timestamp()..date_to.timestamp()) {
+            let low_bucket = (candle.low / bucket_size).floor() as i64;
+            let high_bucket = (candle.high / bucket_size).floor() as i64;
+            let bucket_volume = candle.get_volume() / (high_bucket - low_bucket + 1) as f64;
+
+            for bucket in low_bucket..=high_bucket {
+                *profile.entry(bucket).or_insert(0.0) += bucket_volume;
+            }
+        }
+
+        profile
+    }
+
+    /// Gets q-quantile of closes in range `[date_from, date_to)` with linear interpolation
+    /// between closest ranks. `None` for empty range or `q` out of `[0, 1]`
+    pub fn quantile(&self, date_from: DateTime<Utc>, date_to: DateTime<Utc>, q: f64) -> Option<f64> {
+        if !(0.0..=1.0).contains(&q) {
+            return None;
+        }
+
+        let mut closes: Vec<f64> = self
+            .prices_by_date
+            .range(date_from.timestamp()..date_to.timestamp())
+            .map(|(_, candle)| candle.close)
+            .collect();
+
+        if closes.is_empty() {
+            return None;
+        }
+
+        closes.sort_by(|a, b| a.total_cmp(b));
+        let rank = q * (closes.len() - 1) as f64;
+        let lower = rank.floor() as usize;
+        let upper = rank.ceil() as usize;
+
+        Some(closes[lower] + (closes[upper] - closes[lower]) * (rank - lower as f64))
+    }
+
+    /// Gets min and max of selected price of candles in range `[date_from, date_to)`.
+    /// Returns `None` for ranges without candles
+    pub fn price_range(&self, date_from: DateTime<Utc>, date_to: DateTime<Utc>, selector: fn(&CandleData) -> f64) -> Option<(f64, f64)> {
+        self.prices_by_date
+            .range(date_from.timestamp()..date_to.timestamp())
+            .map(|(_, candle)| selector(candle))
+            .fold(None, |range, price| match range {
+                Some((min, max)) => Some((f64::min(min, price), f64::max(max, price))),
+                None => Some((price, price)),
+            })
+    }
+
+    /// Gets share of periods starting in range `[date_from, date_to)` that have candles.
+    /// Gives 1 for ranges without periods
+    pub fn coverage(&self, date_from: DateTime<Utc>, date_to: DateTime<Utc>) -> f64 {
+        let first_start = self.get_start_date(date_from);
+
+        if date_to <= first_start {
+            return 1.0;
+        }
+
+        let offset = Duration::seconds(self.offset_secs);
+        let expected_count = self
+            .candle_type
+            .get_dates_count(first_start - offset, date_to - Duration::seconds(1) - offset);
+        let actual_count = self
+            .prices_by_date
+            .range(first_start.timestamp()..date_to.timestamp())
+            .count();
+
+        (actual_count as f64 / expected_count as f64).min(1.0)
+    }
+
+    /// Gets candle of the period containing specified date
+    pub fn get_at(&self, datetime: DateTime<Utc>) -> Option<&CandleData> {
+        let timestamp_sec = self.get_start_date(datetime).timestamp();
+
+        self.prices_by_date.get(&timestamp_sec)
+    }
+
+    /// Inserts `(datetime, open, high, low, close, volume)` rows dated by start of their periods.
+    /// Rows violating candle invariants are skipped if `skip_invalid` is set, otherwise nothing
+    /// is inserted and the first violation is returned. Returns inserted count
+    pub fn extend_from<I: IntoIterator<Item = (DateTime<Utc>, f64, f64, f64, f64, f64)>>(
+        &mut self,
+        rows: I,
+        skip_invalid: bool,
+    ) -> Result<usize, CandleSdkError> {
+        let mut candles = Vec::new();
+
+        for (datetime, open, high, low, close, volume) in rows {
+            let candle_date = self.get_start_date(datetime);
+            let candle = CandleData::from_ohlcv(candle_date, open, high, low, close, volume);
+
+            match candle.validate() {
+                Ok(()) => candles.push(candle),
+                Err(_) if skip_invalid => continue,
+                Err(errors) => return Err(CandleSdkError::InvalidCandle { datetime, errors }),
+            }
+        }
+
+        let count = candles.len();
+
+        for candle in candles {
+            self.init(candle);
+        }
+
+        Ok(count)
+    }
+
+    /// Gets candle by its position in date ascending order, 0 is the oldest
+    pub fn get_by_index(&self, index: usize) -> Option<&CandleData> {
+        self.prices_by_date.values().nth(index)
+    }
+
+    /// Gets up to n latest candles ordered by date ascending
+    pub fn last_n(&self, n: usize) -> Vec<CandleData> {
+        let mut result: Vec<CandleData> = self.prices_by_date.values().rev().take(n).cloned().collect();
+        result.reverse();
+
+        result
+    }
+
+    pub fn get_by_date_range_filtered(&self, date_from: DateTime<Utc>, date_to: DateTime<Utc>, min_volume: f64) -> Vec<CandleData>{
+        let timestamp_from = date_from.timestamp();
+        let timestamp_to = date_to.timestamp();
+
+        self.prices_by_date
+            .range(timestamp_from..timestamp_to)
+            .filter(|(_date, candle)| candle.get_volume() >= min_volume)
+            .map(|(_date, candle)| candle.clone())
+            .collect()
+    }
+
+    /// Aggregates candles into a new cache of a coarser candle type.
+    /// Fails if the cache candle type doesn't evenly divide the target type
+    pub fn resample(&self, target_type: CandleType) -> Result<CandlePricesCache, CandleSdkError> {
+        if !target_type.divisors().contains(&self.candle_type) {
+            return Err(CandleSdkError::InvalidResampleType {
+                source: self.candle_type.clone(),
+                target: target_type,
+            });
+        }
+
+        let mut result = CandlePricesCache::new(target_type);
+        result.offset_secs = self.offset_secs;
+
+        for (timestamp, candle) in self.prices_by_date.iter() {
+            let target_date = result.get_start_date(to_datetime(*timestamp));
+            merge_sub_candle(&mut result.prices_by_date, target_date, candle);
+        }
+
+        Ok(result)
+    }
+
+    /// Merges a finished candle of a finer candle type into the candle of its period.
+    /// Fails if the source type doesn't evenly divide the cache candle type.
+    /// Every source candle must be fed once, otherwise its volume is counted again
+    pub fn feed_candle(&mut self, source: &CandleData, source_type: &CandleType) -> Result<(), CandleSdkError> {
+        if !self.candle_type.divisors().contains(source_type) {
+            return Err(CandleSdkError::InvalidResampleType {
+                source: source_type.clone(),
+                target: self.candle_type.clone(),
+            });
+        }
+
+        let target_date = self.get_start_date(source.datetime);
+        merge_sub_candle(&mut self.prices_by_date, target_date, source);
+
+        Ok(())
+    }
+
+    /// Creates zero volume candles for missing periods between cached candles. Returns created count
+    pub fn fill_gaps(&mut self, mode: GapFillMode) -> usize {
+        let mut filled_candles = Vec::new();
+        let candles: Vec<(&i64, &CandleData)> = self.prices_by_date.iter().collect();
+
+        for pair in candles.windows(2) {
+            let (prev_timestamp, prev) = pair[0];
+            let (next_timestamp, next) = pair[1];
+            let offset = Duration::seconds(self.offset_secs);
+            let prev_date = to_datetime(*prev_timestamp);
+            let missing_dates: Vec<DateTime<Utc>> = self
+                .candle_type
+                .get_start_dates(prev_date - offset, to_datetime(*next_timestamp) - offset)
+                .into_iter()
+                .map(|date| date + offset)
+                .filter(|date| date.timestamp() > *prev_timestamp && date.timestamp() < *next_timestamp)
+                .collect();
+            let steps = (missing_dates.len() + 1) as f64;
+
+            for (i, date) in missing_dates.into_iter().enumerate() {
+                let price = match mode {
+                    GapFillMode::Flat => prev.close,
+                    GapFillMode::Linear => prev.close + (next.close - prev.close) * (i + 1) as f64 / steps,
+                };
+
+                filled_candles.push(CandleData::new_flat(date, price));
+            }
+        }
+
+        let filled_count = filled_candles.len();
+
+        for candle in filled_candles {
+            self.init(candle);
+        }
+
+        filled_count
+    }
+
+    /// Selects `threshold` candles using Largest-Triangle-Three-Buckets on close prices.
+    /// Selected candles keep original OHLCV. All candles are returned if threshold is less than 3
+    /// or not less than the candles count
+    pub fn downsample_lttb(&self, threshold: usize) -> Vec<CandleData> {
+        let points: Vec<(f64, &CandleData)> = self
+            .prices_by_date
+            .iter()
+            .map(|(timestamp, candle)| (*timestamp as f64, candle))
+            .collect();
+
+        if threshold < 3 || threshold >= points.len() {
+            return points.into_iter().map(|(_x, candle)| candle.clone()).collect();
+        }
+
+        let mut result = Vec::with_capacity(threshold);
+        let bucket_size = (points.len() - 2) as f64 / (threshold - 2) as f64;
+        let mut selected = 0;
+        result.push(points[0].1.clone());
+
+        for bucket in 0..threshold - 2 {
+            let bucket_start = (bucket as f64 * bucket_size) as usize + 1;
+            let bucket_end = ((bucket + 1) as f64 * bucket_size) as usize + 1;
+            let next_end = (((bucket + 2) as f64 * bucket_size) as usize + 1).min(points.len());
+            let next_bucket = &points[bucket_end..next_end.max(bucket_end + 1)];
+            let next_x = next_bucket.iter().map(|(x, _candle)| x).sum::<f64>() / next_bucket.len() as f64;
+            let next_y = next_bucket.iter().map(|(_x, candle)| candle.close).sum::<f64>() / next_bucket.len() as f64;
+            let (selected_x, selected_candle) = points[selected];
+            let mut max_area = -1.0;
+
+            for (i, (x, candle)) in points.iter().enumerate().take(bucket_end).skip(bucket_start) {
+                let area = ((selected_x - next_x) * (candle.close - selected_candle.close)
+                    - (selected_x - x) * (next_y - selected_candle.close))
+                    .abs();
+
+                if area > max_area {
+                    max_area = area;
+                    selected = i;
+                }
+            }
+
+            result.push(points[selected].1.clone());
+        }
+
+        result.push(points[points.len() - 1].1.clone());
+
+        result
+    }
+
+    /// Gets selected price normalized by population mean and standard deviation.
+    /// All values are 0 for a constant series
+    pub fn zscore(&self, selector: fn(&CandleData) -> f64) -> Vec<(DateTime<Utc>, f64)> {
+        if self.prices_by_date.len() < 2 {
+            return vec![];
+        }
+
+        let count = self.prices_by_date.len() as f64;
+        let mean = self.prices_by_date.values().map(selector).sum::<f64>() / count;
+        let variance = self
+            .prices_by_date
+            .values()
+            .map(|candle| (selector(candle) - mean).powi(2))
+            .sum::<f64>()
+            / count;
+        let std_dev = variance.sqrt();
+
+        self.prices_by_date
+            .iter()
+            .map(|(timestamp, candle)| {
+                let value = if std_dev == 0.0 { 0.0 } else { (selector(candle) - mean) / std_dev };
+
+                (to_datetime(*timestamp), value)
+            })
+            .collect()
+    }
+
+    /// Moves all candles by the offset. Fails without changes if the offset
+    /// is not a whole number of candle periods. Only zero offset is valid for calendar types
+    pub fn shift_time(&mut self, offset: Duration) -> Result<(), CandleSdkError> {
+        let is_aligned = match self.candle_type.try_fixed_duration() {
+            Some(duration) => offset.num_seconds() % duration.num_seconds() == 0,
+            // calendar periods vary in length, so no fixed offset is a whole number of them
+            None => offset.is_zero(),
+        };
+
+        if offset.subsec_nanos() != 0 || !is_aligned {
+            return Err(CandleSdkError::UnalignedOffset {
+                candle_type: self.candle_type.clone(),
+                offset_secs: offset.num_seconds(),
+            });
+        }
+
+        let prices_by_date = std::mem::take(&mut self.prices_by_date);
+
+        for (timestamp, mut candle) in prices_by_date {
+            candle.datetime += offset;
+            self.prices_by_date.insert(timestamp + offset.num_seconds(), candle);
+        }
+
+        Ok(())
+    }
+
+    /// Re-aggregates candles onto period boundaries shifted by `offset_secs` from the candle type
+    /// alignment. The result keeps the offset, so date lookups like `get_at` use shifted periods.
+    /// It's lossy: a candle is moved whole into the shifted period containing its start,
+    /// because ticks inside the candle are not stored
+    pub fn rebucket(&self, offset_secs: i64) -> CandlePricesCache {
+        let mut result = CandlePricesCache::new_with_min_periods(self.candle_type.clone(), self.min_periods);
+        result.offset_secs = offset_secs;
+
+        for (timestamp, candle) in self.prices_by_date.iter() {
+            let target_date = result.get_start_date(to_datetime(*timestamp));
+            merge_sub_candle(&mut result.prices_by_date, target_date, candle);
+        }
+
+        result
+    }
+
+    /// Gets candles in date order flagging the candle containing `now` as live
+    pub fn iter_with_liveness(&self, now: DateTime<Utc>) -> impl Iterator<Item = (CandleData, bool)> + '_ {
+        let live_timestamp = self.get_start_date(now).timestamp();
+
+        self.prices_by_date
+            .iter()
+            .map(move |(timestamp, candle)| (candle.clone(), *timestamp == live_timestamp))
+    }
+
+    /// Gets Pearson correlation of selected prices for candles with the same dates in both caches.
+    /// Returns `None` for less than 2 matched candles or a constant series
+    pub fn correlation(&self, other: &CandlePricesCache, selector: fn(&CandleData) -> f64) -> Option<f64> {
+        let pairs: Vec<(f64, f64)> = self
+            .prices_by_date
+            .iter()
+            .filter_map(|(timestamp, candle)| {
+                let other_candle = other.prices_by_date.get(timestamp)?;
+
+                Some((selector(candle), selector(other_candle)))
+            })
+            .collect();
+
+        if pairs.len() < 2 {
+            return None;
+        }
+
+        let count = pairs.len() as f64;
+        let mean_x = pairs.iter().map(|(x, _y)| x).sum::<f64>() / count;
+        let mean_y = pairs.iter().map(|(_x, y)| y).sum::<f64>() / count;
+        let mut covariance = 0.0;
+        let mut variance_x = 0.0;
+        let mut variance_y = 0.0;
+
+        for (x, y) in pairs.iter() {
+            covariance += (x - mean_x) * (y - mean_y);
+            variance_x += (x - mean_x).powi(2);
+            variance_y += (y - mean_y).powi(2);
+        }
+
+        if variance_x == 0.0 || variance_y == 0.0 {
+            return None;
+        }
+
+        Some(covariance / (variance_x.sqrt() * variance_y.sqrt()))
+    }
+
     pub fn clear(&mut self) {
         self.prices_by_date.clear()
     }
-}
\ No newline at end of file
+
+    /// Removes zero-volume candles with the same OHLC as both neighbors.
+    /// The first and last candle of each flat run are kept. Returns removed count
+    pub fn coalesce_flat(&mut self) -> usize {
+        let candles: Vec<(&i64, &CandleData)> = self.prices_by_date.iter().collect();
+        let mut flat_timestamps = Vec::new();
+
+        for window in candles.windows(3) {
+            let (_, prev) = window[0];
+            let (timestamp, candle) = window[1];
+            let (_, next) = window[2];
+
+            if candle.get_volume() == 0.0 && candle.is_same_prices(prev) && candle.is_same_prices(next) {
+                flat_timestamps.push(*timestamp);
+            }
+        }
+
+        for timestamp in flat_timestamps.iter() {
+            self.prices_by_date.remove(timestamp);
+        }
+
+        flat_timestamps.len()
+    }
+
+    /// Gets close to close returns, either simple or logarithmic. The first candle is skipped
+    pub fn returns(&self, log: bool) -> Vec<(DateTime<Utc>, f64)> {
+        let mut result = Vec::with_capacity(self.prices_by_date.len().saturating_sub(1));
+        let mut prev_close: Option<f64> = None;
+
+        for (timestamp, candle) in self.prices_by_date.iter() {
+            let close = candle.close;
+
+            if let Some(prev_close) = prev_close {
+                let value = if log {
+                    if prev_close <= 0.0 || close <= 0.0 {
+                        None
+                    } else {
+                        Some((close / prev_close).ln())
+                    }
+                } else if prev_close == 0.0 {
+                    None
+                } else {
+                    Some(close / prev_close - 1.0)
+                };
+
+                if let Some(value) = value {
+                    result.push((to_datetime(*timestamp), value));
+                }
+            }
+
+            prev_close = Some(close);
+        }
+
+        result
+    }
+
+    /// Gets exponentially smoothed selected price: `s = alpha * x + (1 - alpha) * s_prev`,
+    /// seeded with the first value
+    pub fn smooth(&self, alpha: f64, selector: fn(&CandleData) -> f64) -> Result<Vec<(DateTime<Utc>, f64)>, CandleSdkError> {
+        if !(alpha > 0.0 && alpha <= 1.0) {
+            return Err(CandleSdkError::InvalidSmoothingFactor { alpha });
+        }
+
+        let mut result = Vec::with_capacity(self.prices_by_date.len());
+        let mut prev_value: Option<f64> = None;
+
+        for (timestamp, candle) in self.prices_by_date.iter() {
+            let value = selector(candle);
+            let smoothed = match prev_value {
+                Some(prev_value) => alpha * value + (1.0 - alpha) * prev_value,
+                None => value,
+            };
+            result.push((to_datetime(*timestamp), smoothed));
+            prev_value = Some(smoothed);
+        }
+
+        Ok(result)
+    }
+
+    /// Gets candle volumes divided by the max volume. All values are 0 if the max volume is 0
+    pub fn normalize_volume(&self) -> Vec<(DateTime<Utc>, f64)> {
+        let max_volume = self
+            .prices_by_date
+            .values()
+            .map(|candle| candle.get_volume())
+            .fold(0.0, f64::max);
+
+        self.prices_by_date
+            .iter()
+            .map(|(timestamp, candle)| {
+                let value = if max_volume > 0.0 { candle.get_volume() / max_volume } else { 0.0 };
+
+                (to_datetime(*timestamp), value)
+            })
+            .collect()
+    }
+
+    /// Gets population standard deviation of log-returns scaled by `sqrt(periods_per_year)`,
+    /// e.g. 252 for daily candles. `None` for fewer than 2 candles
+    pub fn annualized_volatility(&self, periods_per_year: f64, selector: fn(&CandleData) -> f64) -> Option<f64> {
+        if self.prices_by_date.len() < 2 {
+            return None;
+        }
+
+        let returns: Vec<f64> = self.log_returns(selector).into_iter().map(|(_, value)| value).collect();
+
+        if returns.is_empty() {
+            return None;
+        }
+
+        let count = returns.len() as f64;
+        let mean = returns.iter().sum::<f64>() / count;
+        let variance = returns.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / count;
+
+        Some(variance.sqrt() * periods_per_year.sqrt())
+    }
+
+    /// Gets log-returns of selected price between consecutive candles.
+    /// Pairs with non-positive prices are skipped
+    pub fn log_returns(&self, selector: fn(&CandleData) -> f64) -> Vec<(DateTime<Utc>, f64)> {
+        let candles: Vec<(&i64, &CandleData)> = self.prices_by_date.iter().collect();
+
+        candles
+            .windows(2)
+            .filter_map(|pair| {
+                let prev_price = selector(pair[0].1);
+                let price = selector(pair[1].1);
+
+                if prev_price <= 0.0 || price <= 0.0 {
+                    return None;
+                }
+
+                Some((to_datetime(*pair[1].0), (price / prev_price).ln()))
+            })
+            .collect()
+    }
+}
+
+/// Merges candle of a finer type into the candle of the period starting at `target_date`.
+/// A source candle not built by resampling counts as one sub-candle
+fn merge_sub_candle(prices_by_date: &mut BTreeMap<i64, CandleData>, target_date: DateTime<Utc>, source: &CandleData) {
+    let sub_candle_count = source.sub_candle_count.max(1);
+
+    match prices_by_date.get_mut(&target_date.timestamp()) {
+        Some(target_candle) => {
+            let total_count = target_candle.sub_candle_count + sub_candle_count;
+            target_candle.merge(source);
+            target_candle.sub_candle_count = total_count;
+        }
+        None => {
+            let mut target_candle = source.clone();
+            target_candle.sub_candle_count = sub_candle_count;
+            prices_by_date.insert(target_date.timestamp(), target_candle);
+        }
+    }
+}
+
+fn to_datetime(timestamp_sec: i64) -> DateTime<Utc> {
+    Utc.timestamp_opt(timestamp_sec, 0).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::caches::candle_prices_cache::CandlePricesCache;
+    use crate::models::{candle_data::CandleData, candle_sdk_error::CandleSdkError, candle_type::CandleType, candle_validation_error::CandleValidationError, gap_fill_mode::GapFillMode};
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    #[tokio::test]
+    async fn coalesce_flat() {
+        let mut cache = CandlePricesCache::new(CandleType::Minute);
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        cache.init(CandleData::new(from, 10.0, 5.0));
+
+        for i in 1..4 {
+            cache.init(CandleData::new(from + Duration::minutes(i), 11.0, 0.0));
+        }
+
+        cache.init(CandleData::new(from + Duration::minutes(4), 12.0, 3.0));
+
+        let removed = cache.coalesce_flat();
+
+        assert_eq!(removed, 1);
+        assert_eq!(cache.prices_by_date.len(), 4);
+        assert!(cache.prices_by_date.contains_key(&(from + Duration::minutes(1)).timestamp()));
+        assert!(!cache.prices_by_date.contains_key(&(from + Duration::minutes(2)).timestamp()));
+        assert!(cache.prices_by_date.contains_key(&(from + Duration::minutes(3)).timestamp()));
+    }
+
+    #[tokio::test]
+    async fn simple_returns() {
+        let cache = create_cache(&[100.0, 110.0, 99.0]);
+
+        let returns = cache.returns(false);
+
+        assert_eq!(returns.len(), 2);
+        assert!((returns[0].1 - 0.1).abs() < 1e-12);
+        assert!((returns[1].1 + 0.1).abs() < 1e-12);
+        assert_eq!(returns[0].0, Utc.with_ymd_and_hms(2000, 1, 1, 0, 1, 0).unwrap());
+    }
+
+    #[tokio::test]
+    async fn log_returns() {
+        let cache = create_cache(&[100.0, 110.0, 99.0]);
+
+        let returns = cache.returns(true);
+
+        assert_eq!(returns.len(), 2);
+        assert!((returns[0].1 - 1.1_f64.ln()).abs() < 1e-12);
+        assert!((returns[1].1 - 0.9_f64.ln()).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn log_returns_skip_non_positive() {
+        let cache = create_cache(&[100.0, 0.0, 99.0]);
+
+        assert!(cache.returns(true).is_empty());
+    }
+
+    #[tokio::test]
+    async fn get_by_date_range_filtered() {
+        let mut cache = CandlePricesCache::new(CandleType::Minute);
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let volumes = [100.0, 5.0, 50.0, 0.0, 75.0];
+
+        for (i, volume) in volumes.iter().enumerate() {
+            cache.init(CandleData::new(from + Duration::minutes(i as i64), 1.0, *volume));
+        }
+
+        let candles = cache.get_by_date_range_filtered(from, from + Duration::minutes(4), 50.0);
+
+        let volumes: Vec<f64> = candles.iter().map(|candle| candle.get_volume()).collect();
+        assert_eq!(volumes, vec![100.0, 50.0]);
+    }
+
+    #[tokio::test]
+    async fn resample() {
+        let mut cache = CandlePricesCache::new(CandleType::Minute);
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in 0..60 {
+            let datetime = from + Duration::minutes(i);
+            let price = 100.0 + ((i * 7) % 13) as f64;
+            let mut candle = CandleData::new(datetime, price, 1.0);
+            candle.update(datetime + Duration::seconds(30), price + 0.5, 2.0);
+            cache.init(candle);
+        }
+
+        let resampled = cache.resample(CandleType::Hour).unwrap();
+
+        assert_eq!(resampled.candle_type, CandleType::Hour);
+        assert_eq!(resampled.prices_by_date.len(), 1);
+        let candle = resampled.prices_by_date.get(&from.timestamp()).unwrap();
+        let sources: Vec<&CandleData> = cache.prices_by_date.values().collect();
+        assert_eq!(candle.open, sources[0].open);
+        assert_eq!(candle.close, sources[59].close);
+        assert_eq!(candle.high, sources.iter().map(|c| c.high).fold(f64::MIN, f64::max));
+        assert_eq!(candle.low, sources.iter().map(|c| c.low).fold(f64::MAX, f64::min));
+        assert_eq!(candle.volume, 180.0);
+    }
+
+    #[tokio::test]
+    async fn feed_candle() {
+        let mut cache = CandlePricesCache::new(CandleType::Hour);
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let mut sources = Vec::new();
+
+        for i in 0..12 {
+            let datetime = from + Duration::minutes(i * 5);
+            let price = 100.0 + ((i * 7) % 11) as f64;
+            let mut source = CandleData::new(datetime, price, 1.0);
+            source.update(datetime + Duration::minutes(4), price + 0.5, 2.0);
+            cache.feed_candle(&source, &CandleType::FiveMinutes).unwrap();
+            sources.push(source);
+        }
+
+        assert_eq!(cache.prices_by_date.len(), 1);
+        let candle = cache.get_at(from).unwrap();
+        assert_eq!(candle.open, sources[0].open);
+        assert_eq!(candle.close, sources[11].close);
+        assert_eq!(candle.high, sources.iter().map(|c| c.high).fold(f64::MIN, f64::max));
+        assert_eq!(candle.low, sources.iter().map(|c| c.low).fold(f64::MAX, f64::min));
+        assert_eq!(candle.volume, 36.0);
+        assert_eq!(candle.tick_count, 24);
+        assert_eq!(candle.datetime, from + Duration::minutes(59));
+        assert_eq!(candle.completeness(&CandleType::Hour, &CandleType::FiveMinutes), 1.0);
+
+        let result = cache.feed_candle(&sources[0], &CandleType::FortyFiveMinutes);
+
+        assert!(matches!(result, Err(CandleSdkError::InvalidResampleType { .. })));
+        assert_eq!(cache.get_at(from).unwrap().volume, 36.0);
+    }
+
+    #[tokio::test]
+    async fn resample_resampled_completeness() {
+        let mut cache = CandlePricesCache::new(CandleType::Minute);
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in 0..45 {
+            cache.init(CandleData::new(from + Duration::minutes(i), 100.0, 1.0));
+        }
+
+        let resampled = cache.resample(CandleType::FifteenMinutes).unwrap().resample(CandleType::Hour).unwrap();
+
+        let candle = resampled.prices_by_date.get(&from.timestamp()).unwrap();
+        assert_eq!(candle.sub_candle_count, 45);
+        assert!((candle.completeness(&CandleType::Hour, &CandleType::Minute) - 0.75).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn resample_completeness() {
+        let mut cache = CandlePricesCache::new(CandleType::Minute);
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in 0..45 {
+            cache.init(CandleData::new(from + Duration::minutes(i), 100.0, 1.0));
+        }
+
+        let resampled = cache.resample(CandleType::Hour).unwrap();
+
+        let candle = resampled.prices_by_date.get(&from.timestamp()).unwrap();
+        assert_eq!(candle.sub_candle_count, 45);
+        assert!((candle.completeness(&CandleType::Hour, &CandleType::Minute) - 0.75).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn resample_to_finer_type() {
+        let cache = CandlePricesCache::new(CandleType::Hour);
+
+        assert!(cache.resample(CandleType::Hour).is_err());
+        assert!(cache.resample(CandleType::Minute).is_err());
+    }
+
+    #[tokio::test]
+    async fn resample_to_not_divisible_type() {
+        let cache = CandlePricesCache::new(CandleType::FortyFiveMinutes);
+
+        assert!(cache.resample(CandleType::Hour).is_err());
+        assert!(cache.resample(CandleType::SixHours).is_ok());
+    }
+
+    #[tokio::test]
+    async fn fill_gaps_flat() {
+        let mut cache = CandlePricesCache::new(CandleType::Minute);
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        cache.init(CandleData::new(from, 100.0, 1.0));
+        cache.init(CandleData::new(from + Duration::minutes(3), 130.0, 1.0));
+
+        let filled = cache.fill_gaps(GapFillMode::Flat);
+
+        assert_eq!(filled, 2);
+        let closes: Vec<f64> = cache.prices_by_date.values().map(|candle| candle.close).collect();
+        assert_eq!(closes, vec![100.0, 100.0, 100.0, 130.0]);
+    }
+
+    #[tokio::test]
+    async fn fill_gaps_linear() {
+        let mut cache = CandlePricesCache::new(CandleType::Minute);
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        cache.init(CandleData::new(from, 100.0, 1.0));
+        cache.init(CandleData::new(from + Duration::minutes(3), 130.0, 1.0));
+
+        let filled = cache.fill_gaps(GapFillMode::Linear);
+
+        assert_eq!(filled, 2);
+        let first = cache.prices_by_date.get(&(from + Duration::minutes(1)).timestamp()).unwrap();
+        let second = cache.prices_by_date.get(&(from + Duration::minutes(2)).timestamp()).unwrap();
+        assert!((first.close - 110.0).abs() < 1e-9);
+        assert!((second.close - 120.0).abs() < 1e-9);
+        assert_eq!(first.open, first.close);
+        assert_eq!(second.volume, 0.0);
+    }
+
+    #[tokio::test]
+    async fn log_returns_by_selector() {
+        let flat_cache = create_cache(&[100.0, 100.0, 100.0]);
+        let growing_cache = create_cache(&[100.0, 101.0]);
+
+        let flat_returns = flat_cache.log_returns(|candle| candle.close);
+        let growing_returns = growing_cache.log_returns(|candle| candle.close);
+
+        assert_eq!(flat_returns.len(), 2);
+        assert!(flat_returns.iter().all(|(_date, value)| *value == 0.0));
+        assert_eq!(growing_returns.len(), 1);
+        assert!((growing_returns[0].1 - 0.00995).abs() < 1e-5);
+        assert!(create_cache(&[100.0]).log_returns(|candle| candle.close).is_empty());
+    }
+
+    #[tokio::test]
+    async fn downsample_lttb_keeps_spike() {
+        let mut closes: Vec<f64> = (0..100).map(|i| 100.0 + (i % 5) as f64 * 0.1).collect();
+        closes[57] = 250.0;
+        let cache = create_cache(&closes);
+
+        let candles = cache.downsample_lttb(10);
+
+        assert_eq!(candles.len(), 10);
+        assert_eq!(candles[0].close, closes[0]);
+        assert_eq!(candles[9].close, closes[99]);
+        let spike = candles.iter().find(|candle| candle.close == 250.0).unwrap();
+        assert_eq!(spike, cache.prices_by_date.values().nth(57).unwrap());
+        assert_eq!(cache.downsample_lttb(200).len(), 100);
+    }
+
+    #[tokio::test]
+    async fn zscore() {
+        let cache = create_cache(&[10.0, 12.5, 7.0, 30.0, 18.0, 3.0, 21.0]);
+
+        let scores = cache.zscore(|candle| candle.close);
+
+        let count = scores.len() as f64;
+        let mean = scores.iter().map(|(_date, value)| value).sum::<f64>() / count;
+        let std_dev = (scores.iter().map(|(_date, value)| (value - mean).powi(2)).sum::<f64>() / count).sqrt();
+        assert_eq!(scores.len(), 7);
+        assert!(mean.abs() < 1e-9);
+        assert!((std_dev - 1.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn zscore_constant_series() {
+        let cache = create_cache(&[10.0, 10.0, 10.0]);
+
+        let scores = cache.zscore(|candle| candle.close);
+
+        assert!(scores.iter().all(|(_date, value)| *value == 0.0));
+        assert!(create_cache(&[10.0]).zscore(|candle| candle.close).is_empty());
+    }
+
+    #[tokio::test]
+    async fn shift_time() {
+        let mut cache = CandlePricesCache::new(CandleType::Hour);
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in 0..3 {
+            cache.init(CandleData::new(from + Duration::hours(i), 100.0 + i as f64, 1.0));
+        }
+
+        cache.shift_time(Duration::hours(1)).unwrap();
+
+        let keys: Vec<i64> = cache.prices_by_date.keys().cloned().collect();
+        assert_eq!(keys, (1..4).map(|i| (from + Duration::hours(i)).timestamp()).collect::<Vec<i64>>());
+        let first = cache.prices_by_date.values().next().unwrap();
+        assert_eq!(first.datetime, from + Duration::hours(1));
+        assert_eq!(first.close, 100.0);
+    }
+
+    #[tokio::test]
+    async fn shift_time_unaligned() {
+        let mut cache = CandlePricesCache::new(CandleType::Hour);
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        cache.init(CandleData::new(from, 100.0, 1.0));
+
+        let result = cache.shift_time(Duration::minutes(30));
+
+        assert!(matches!(result, Err(CandleSdkError::UnalignedOffset { offset_secs: 1800, .. })));
+        assert!(cache.prices_by_date.contains_key(&from.timestamp()));
+        assert!(CandlePricesCache::new(CandleType::Hour).shift_time(Duration::minutes(30)).is_err());
+
+        let mut monthly_cache = CandlePricesCache::new(CandleType::Monthly);
+        monthly_cache.init(CandleData::new(from, 100.0, 1.0));
+        assert!(monthly_cache.shift_time(Duration::days(31)).is_err());
+        assert!(monthly_cache.shift_time(Duration::zero()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn rebucket() {
+        let mut cache = CandlePricesCache::new(CandleType::Hour);
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in 0..4 {
+            cache.update(from + Duration::hours(i), 100.0 + i as f64, 1.0);
+            cache.update(from + Duration::hours(i) + Duration::minutes(50), 101.0 + i as f64, 2.0);
+        }
+
+        let rebucketed = cache.rebucket(1800);
+
+        let keys: Vec<i64> = rebucketed.prices_by_date.keys().cloned().collect();
+        let expected: Vec<i64> = (0..4)
+            .map(|i| (from - Duration::minutes(30) + Duration::hours(i)).timestamp())
+            .collect();
+        assert_eq!(keys, expected);
+        assert_eq!(rebucketed.offset_secs, 1800);
+        assert_eq!(rebucketed.candle_type, CandleType::Hour);
+        assert_eq!(rebucketed.prices_by_date.values().map(|candle| candle.get_volume()).sum::<f64>(), 12.0);
+        assert!(rebucketed.prices_by_date.values().all(|candle| candle.sub_candle_count == 1));
+        assert_eq!(rebucketed.get_at(from + Duration::minutes(10)).unwrap().open, 100.0);
+        assert_eq!(rebucketed.get_at(from + Duration::minutes(40)).unwrap().open, 101.0);
+        assert_eq!(rebucketed.get_start_date(from + Duration::minutes(40)), from + Duration::minutes(30));
+
+        let not_shifted = cache.rebucket(0);
+        assert_eq!(not_shifted.prices_by_date.keys().collect::<Vec<_>>(), cache.prices_by_date.keys().collect::<Vec<_>>());
+        assert_eq!(not_shifted.get_at(from).unwrap().close, cache.get_at(from).unwrap().close);
+    }
+
+    #[tokio::test]
+    async fn rebucket_merges_straddling_candles() {
+        let mut cache = CandlePricesCache::new(CandleType::Hour);
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        cache.init(CandleData::new(from + Duration::minutes(45), 100.0, 1.0));
+        cache.init(CandleData::new(from + Duration::minutes(60), 101.0, 2.0));
+
+        let rebucketed = cache.rebucket(1800);
+
+        let candle = rebucketed.get_at(from + Duration::minutes(50)).unwrap();
+        assert_eq!(rebucketed.len(), 1);
+        assert_eq!(candle.sub_candle_count, 2);
+        assert_eq!(candle.get_volume(), 3.0);
+    }
+
+    #[tokio::test]
+    async fn iter_with_liveness() {
+        let cache = create_cache(&[1.0, 2.0, 3.0, 4.0]);
+        let now = Utc.with_ymd_and_hms(2000, 1, 1, 0, 3, 25).unwrap();
+
+        let flags: Vec<bool> = cache.iter_with_liveness(now).map(|(_candle, is_live)| is_live).collect();
+
+        assert_eq!(flags, vec![false, false, false, true]);
+        assert!(cache.iter_with_liveness(now + Duration::minutes(1)).all(|(_candle, is_live)| !is_live));
+    }
+
+    #[tokio::test]
+    async fn correlation() {
+        let closes = [10.0, 12.0, 11.0, 15.0, 14.0, 18.0];
+        let negated: Vec<f64> = closes.iter().map(|close| -close).collect();
+        let cache = create_cache(&closes);
+
+        let self_correlation = cache.correlation(&cache, |candle| candle.close).unwrap();
+        let negated_correlation = cache.correlation(&create_cache(&negated), |candle| candle.close).unwrap();
+        let independent_correlation = create_cache(&[1.0, 2.0, 3.0, 4.0, 5.0])
+            .correlation(&create_cache(&[2.0, 4.0, 1.0, 4.0, 2.0]), |candle| candle.close)
+            .unwrap();
+
+        assert!((self_correlation - 1.0).abs() < 1e-12);
+        assert!((negated_correlation + 1.0).abs() < 1e-12);
+        assert!(independent_correlation.abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn correlation_without_overlap() {
+        let cache = create_cache(&[1.0, 2.0, 3.0]);
+        let mut other = CandlePricesCache::new(CandleType::Minute);
+        other.init(CandleData::new(Utc.with_ymd_and_hms(2001, 1, 1, 0, 0, 0).unwrap(), 1.0, 1.0));
+
+        assert_eq!(cache.correlation(&other, |candle| candle.close), None);
+        assert_eq!(cache.correlation(&create_cache(&[1.0]), |candle| candle.close), None);
+    }
+
+    #[tokio::test]
+    async fn sma() {
+        let cache = create_cache(&[1.0, 2.0, 3.0, 4.0]);
+        let sma = cache.sma(2);
+
+        assert_eq!(sma.len(), 3);
+        assert_eq!(sma[0], (Utc.with_ymd_and_hms(2000, 1, 1, 0, 1, 0).unwrap(), 1.5));
+        assert_eq!(sma[2].1, 3.5);
+        assert!(cache.sma(5).is_empty());
+        assert!(cache.sma(0).is_empty());
+    }
+
+    #[tokio::test]
+    async fn min_periods() {
+        let mut cache = CandlePricesCache::new_with_min_periods(CandleType::Minute, 3);
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        cache.init(CandleData::new(from, 1.0, 1.0));
+        cache.init(CandleData::new(from + Duration::minutes(1), 2.0, 1.0));
+
+        assert!(!cache.has_sufficient_history(3));
+        assert!(cache.has_sufficient_history(2));
+        assert!(cache.sma(2).is_empty());
+
+        cache.init(CandleData::new(from + Duration::minutes(2), 3.0, 1.0));
+
+        assert_eq!(cache.sma(2).len(), 2);
+    }
+
+    #[tokio::test]
+    #[should_panic]
+    async fn zero_min_periods() {
+        CandlePricesCache::new_with_min_periods(CandleType::Minute, 0);
+    }
+
+    #[tokio::test]
+    async fn last_n() {
+        let closes: Vec<f64> = (0..20).map(|i| i as f64).collect();
+        let cache = create_cache(&closes);
+        let last = cache.last_n(5);
+
+        assert_eq!(last.iter().map(|candle| candle.close).collect::<Vec<f64>>(), vec![15.0, 16.0, 17.0, 18.0, 19.0]);
+        assert_eq!(cache.last_n(50).len(), 20);
+        assert!(cache.last_n(0).is_empty());
+    }
+
+    #[tokio::test]
+    async fn get_at() {
+        let mut cache = CandlePricesCache::new(CandleType::Hour);
+        let start: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 5, 0, 0).unwrap();
+        cache.update(start, 1.0, 1.0);
+        cache.update(start + Duration::minutes(20), 2.0, 1.0);
+
+        let candle = cache.get_at(start).unwrap();
+
+        assert_eq!(candle.open, 1.0);
+        assert_eq!(candle.close, 2.0);
+        assert_eq!(cache.get_at(start + Duration::seconds(1799)), Some(candle));
+        assert_eq!(cache.get_at(start + Duration::hours(1)), None);
+    }
+
+    #[tokio::test]
+    async fn volume_profile() {
+        let mut cache = CandlePricesCache::new(CandleType::Minute);
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        cache.update(from, 10.5, 3.0);
+        cache.update(from + Duration::seconds(10), 12.5, 3.0);
+        cache.update(from + Duration::minutes(1), 12.2, 2.0);
+        cache.update(from + Duration::minutes(2), 20.0, 100.0);
+
+        let profile = cache.volume_profile(from, from + Duration::minutes(2), 1.0);
+
+        assert_eq!(profile.keys().cloned().collect::<Vec<i64>>(), vec![10, 11, 12]);
+        assert_eq!(profile[&10], 2.0);
+        assert_eq!(profile[&11], 2.0);
+        assert_eq!(profile[&12], 4.0);
+        assert_eq!(profile.values().sum::<f64>(), 8.0);
+        assert!(cache.volume_profile(from, from + Duration::minutes(2), 0.0).is_empty());
+    }
+
+    #[tokio::test]
+    async fn annualized_volatility() {
+        let cache = create_cache(&[1.0, 2.0, 1.0, 2.0, 1.0]);
+        let volatility = cache.annualized_volatility(252.0, |candle| candle.close).unwrap();
+
+        assert!((volatility - 2f64.ln() * 252f64.sqrt()).abs() < 1e-12);
+        assert_eq!(create_cache(&[1.0, 1.0, 1.0]).annualized_volatility(252.0, |candle| candle.close), Some(0.0));
+        assert_eq!(create_cache(&[1.0]).annualized_volatility(252.0, |candle| candle.close), None);
+    }
+
+    #[tokio::test]
+    async fn extend_from() {
+        let mut cache = CandlePricesCache::new(CandleType::Hour);
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let rows = vec![
+            (from, 1.0, 1.5, 0.5, 1.2, 10.0),
+            (from + Duration::minutes(90), 1.2, 1.3, 1.1, 1.1, 5.0),
+            (from + Duration::hours(2), 1.1, 1.4, 1.0, 1.3, 7.0),
+        ];
+
+        assert_eq!(cache.extend_from(rows, false), Ok(3));
+        assert_eq!(cache.len(), 3);
+
+        let candle = cache.get_at(from + Duration::hours(1)).unwrap();
+
+        assert_eq!(candle.datetime, from + Duration::hours(1));
+        assert_eq!((candle.open, candle.high, candle.low, candle.close, candle.volume), (1.2, 1.3, 1.1, 1.1, 5.0));
+
+        let invalid_rows = vec![
+            (from + Duration::hours(3), 1.0, 1.5, 0.5, 1.2, 10.0),
+            (from + Duration::hours(4), 1.0, 0.5, 1.5, 1.2, 10.0),
+        ];
+
+        assert!(matches!(
+            cache.extend_from(invalid_rows.clone(), false),
+            Err(CandleSdkError::InvalidCandle { errors, .. }) if errors.contains(&CandleValidationError::HighBelowLow)
+        ));
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.extend_from(invalid_rows, true), Ok(1));
+        assert_eq!(cache.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn smooth() {
+        let closes = [1.0, 3.0, 2.0, 5.0];
+        let cache = create_cache(&closes);
+        let smoothed = cache.smooth(1.0, |candle| candle.close).unwrap();
+
+        assert_eq!(smoothed.iter().map(|(_, value)| *value).collect::<Vec<f64>>(), closes.to_vec());
+
+        let smoothed = cache.smooth(0.5, |candle| candle.close).unwrap();
+
+        assert_eq!(smoothed.iter().map(|(_, value)| *value).collect::<Vec<f64>>(), vec![1.0, 2.0, 2.0, 3.5]);
+        assert_eq!(smoothed[3].0, Utc.with_ymd_and_hms(2000, 1, 1, 0, 3, 0).unwrap());
+
+        for alpha in [0.0, -0.1, 1.1, f64::NAN] {
+            assert!(matches!(
+                cache.smooth(alpha, |candle| candle.close),
+                Err(CandleSdkError::InvalidSmoothingFactor { .. })
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn quantile() {
+        let cache = create_cache(&[5.0, 1.0, 4.0, 2.0, 3.0, 100.0]);
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let to = from + Duration::minutes(5);
+
+        assert_eq!(cache.quantile(from, to, 0.5), Some(3.0));
+        assert_eq!(cache.quantile(from, to, 0.0), Some(1.0));
+        assert_eq!(cache.quantile(from, to, 1.0), Some(5.0));
+        assert_eq!(cache.quantile(from, to, 0.125), Some(1.5));
+        assert_eq!(cache.quantile(from, to + Duration::minutes(1), 0.5), Some(3.5));
+        assert_eq!(cache.quantile(from, to, 1.5), None);
+        assert_eq!(cache.quantile(from, to, -0.1), None);
+        assert_eq!(cache.quantile(to + Duration::minutes(1), to + Duration::minutes(2), 0.5), None);
+    }
+
+    #[tokio::test]
+    async fn get_by_index() {
+        let mut cache = CandlePricesCache::new(CandleType::Minute);
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in [7, 2, 9, 0, 5, 1, 8, 3, 6, 4] {
+            cache.init(CandleData::new(from + Duration::minutes(i), i as f64, 1.0));
+        }
+
+        assert_eq!(cache.get_by_index(0).unwrap().datetime, from);
+        assert_eq!(cache.get_by_index(9).unwrap().datetime, from + Duration::minutes(9));
+        assert_eq!(cache.get_by_index(4).unwrap().close, 4.0);
+        assert_eq!(cache.get_by_index(10), None);
+    }
+
+    #[tokio::test]
+    async fn seeded() {
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 30, 0).unwrap();
+        let mut cache = CandlePricesCache::seeded(CandleType::Hour, from, from + Duration::hours(23));
+
+        assert_eq!(cache.len(), 24);
+        assert!(cache.prices_by_date.values().all(|candle| candle.volume == 0.0 && candle.tick_count == 0));
+        assert_eq!(cache.get_by_index(0).unwrap().datetime, Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap());
+
+        cache.update(from, 1.0, 2.0);
+
+        assert_eq!(cache.len(), 24);
+        assert_eq!(cache.get_at(from).unwrap().close, 1.0);
+        assert_eq!(cache.get_at(from).unwrap().volume, 2.0);
+    }
+
+    #[tokio::test]
+    async fn normalize_volume() {
+        let mut cache = create_cache(&[1.0, 1.0, 1.0, 1.0]);
+
+        for (i, candle) in cache.prices_by_date.values_mut().enumerate() {
+            candle.volume = [2.0, 8.0, 0.0, 4.0][i];
+        }
+
+        let normalized = cache.normalize_volume();
+
+        assert_eq!(normalized.iter().map(|(_, value)| *value).collect::<Vec<f64>>(), vec![0.25, 1.0, 0.0, 0.5]);
+        assert!(normalized.iter().all(|(_, value)| (0.0..=1.0).contains(value)));
+
+        for candle in cache.prices_by_date.values_mut() {
+            candle.volume = 0.0;
+        }
+
+        assert!(cache.normalize_volume().iter().all(|(_, value)| *value == 0.0));
+    }
+
+    #[tokio::test]
+    async fn coverage() {
+        let mut cache = create_cache(&[1.0; 10]);
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let to = from + Duration::minutes(10);
+
+        assert_eq!(cache.coverage(from, to), 1.0);
+
+        cache.prices_by_date.remove(&(from + Duration::minutes(4)).timestamp());
+
+        assert!((cache.coverage(from, to) - 0.9).abs() < 1e-12);
+        assert_eq!(cache.coverage(from, from + Duration::minutes(20)), 0.45);
+        assert_eq!(cache.coverage(from, from), 1.0);
+        assert_eq!(cache.coverage(to, from), 1.0);
+    }
+
+    #[tokio::test]
+    async fn price_range() {
+        let cache = create_cache(&[5.0, 10.0, 3.0, 8.0, 2.0]);
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let to = from + Duration::minutes(5);
+
+        assert_eq!(cache.price_range(from, to, |candle| candle.close), Some((2.0, 10.0)));
+        assert_eq!(cache.price_range(from, from + Duration::minutes(1), |candle| candle.close), Some((5.0, 5.0)));
+        assert_eq!(cache.price_range(from + Duration::minutes(2), to, |candle| candle.high), Some((2.0, 8.0)));
+        assert_eq!(cache.price_range(to, to + Duration::minutes(5), |candle| candle.close), None);
+    }
+
+    fn create_cache(closes: &[f64]) -> CandlePricesCache {
+        let mut cache = CandlePricesCache::new(CandleType::Minute);
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for (i, close) in closes.iter().enumerate() {
+            cache.init(CandleData::new(from + Duration::minutes(i as i64), *close, 1.0));
+        }
+
+        cache
+    }
+}
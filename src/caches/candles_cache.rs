@@ -1,12 +1,40 @@
-use crate::models::{candle::BidAskCandle, candle_data::CandleData, candle_type::CandleType};
-use ahash::AHashMap;
-use chrono::{DateTime, Utc};
-use compact_str::{ToCompactString};
+use crate::caches::cache_diff::CacheDiff;
+use crate::caches::candle_prices_cache::CandlePricesCache;
+use crate::caches::candle_indexes::CandleIndexes;
+use crate::caches::candles_cache_builder::CandlesCacheBuilder;
+use crate::models::{candle::BidAskCandle, candle_data::CandleData, candle_sdk_error::CandleSdkError, candle_type::CandleType, candle_validation_error::CandleValidationError, import_error::ImportError, ohlcv::Ohlcv, open_policy::OpenPolicy, session::Session};
+use ahash::{AHashMap, AHashSet};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use compact_str::{CompactString, ToCompactString};
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+
+const CSV_HEADER: &str = "instrument,candle_type,datetime,\
+bid_open,bid_close,bid_high,bid_low,bid_datetime,bid_volume,bid_tick_count,bid_sub_candle_count,\
+ask_open,ask_close,ask_high,ask_low,ask_datetime,ask_volume,ask_tick_count,ask_sub_candle_count,session";
+const CSV_COLUMNS_COUNT: usize = 20;
+/// Count of updates buffered for a subscriber before newer updates are dropped
+pub const UPDATES_CAPACITY: usize = 1024;
 
 pub struct CandlesCache {
     candles_by_ids: AHashMap<String, BidAskCandle>,
+    indexes: CandleIndexes,
     pub candle_types: Vec<CandleType>,
     pub last_update_date: Option<DateTime<Utc>>,
+    /// Max count of stored candles, must be positive. The oldest finished candles are removed on overflow
+    pub max_capacity: Option<usize>,
+    /// Max allowed distance of tick date ahead of current time. Ticks further in the future are ignored
+    pub reject_future_ticks: Option<Duration>,
+    pub open_policy: OpenPolicy,
+    /// Max distance of tick date past a period start to attribute the tick to the existing previous candle
+    pub boundary_tolerance: Duration,
+    /// Ignores ticks with NaN or infinite prices and volumes
+    pub reject_non_finite: bool,
+    /// Gets session of created candles by their start date. Candles are `Session::Regular` if not set
+    pub session_classifier: Option<Arc<dyn Fn(DateTime<Utc>) -> Session + Send + Sync>>,
+    update_senders: Vec<SyncSender<(CompactString, CandleType)>>,
 }
 
 impl CandlesCache {
@@ -17,9 +45,40 @@ impl CandlesCache {
 
         Self {
             candles_by_ids: AHashMap::new(),
+            indexes: CandleIndexes::default(),
             candle_types,
             last_update_date: None,
+            max_capacity: None,
+            reject_future_ticks: None,
+            open_policy: OpenPolicy::default(),
+            boundary_tolerance: Duration::zero(),
+            reject_non_finite: false,
+            session_classifier: None,
+            update_senders: Vec::new(),
+        }
+    }
+
+    /// Creates cache if every candle type is requested once, otherwise returns the duplicated types
+    pub fn try_new(candle_types: Vec<CandleType>) -> Result<Self, CandleSdkError> {
+        let mut sorted_types = candle_types.clone();
+        sorted_types.sort();
+        let mut duplicates: Vec<CandleType> = sorted_types
+            .windows(2)
+            .filter(|pair| pair[0] == pair[1])
+            .map(|pair| pair[0].clone())
+            .collect();
+
+        if !duplicates.is_empty() {
+            duplicates.dedup();
+
+            return Err(CandleSdkError::DuplicateCandleTypes { candle_types: duplicates });
         }
+
+        Ok(Self::new(candle_types))
+    }
+
+    pub fn builder() -> CandlesCacheBuilder {
+        CandlesCacheBuilder::new()
     }
 
     pub fn get_all(&self) -> &AHashMap<String, BidAskCandle> {
@@ -30,10 +89,38 @@ impl CandlesCache {
         self.candles_by_ids.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.candles_by_ids.is_empty()
+    }
+
+    /// Gets count of unique instruments without allocating
+    pub fn total_instruments(&self) -> usize {
+        self.indexes.ids_by_instrument.len()
+    }
+
+    /// Gets cached instruments in alphabetical order
+    pub fn instruments_sorted(&self) -> Vec<&str> {
+        let mut instruments: Vec<&str> = self
+            .indexes
+            .ids_by_instrument
+            .keys()
+            .map(|instrument| instrument.as_str())
+            .collect();
+        instruments.sort_unstable();
+
+        instruments
+    }
+
     pub fn contains(&self, candle_id: &str) -> bool {
         self.candles_by_ids.contains_key(candle_id)
     }
 
+    /// Checks if any candle of the instrument and candle type is cached
+    pub fn contains_series(&self, instrument: &str, candle_type: &CandleType) -> bool {
+        self.indexes.contains_series(instrument, candle_type)
+    }
+
+    /// Inserts candle. A cached candle with the same id is silently replaced
     pub fn insert(&mut self, candle: BidAskCandle) {
         #[cfg(feature = "console-log")]
         println!(
@@ -44,7 +131,177 @@ impl CandlesCache {
             self.candles_by_ids.len() + 1
         );
 
-        self.candles_by_ids.insert(candle.get_id(), candle);
+        let id = candle.get_id();
+
+        match self.candles_by_ids.get(&id) {
+            Some(replaced_candle) => self.indexes.remove(&id, replaced_candle),
+            None => Self::ensure_capacity(&mut self.candles_by_ids, &mut self.indexes, self.max_capacity),
+        }
+
+        self.indexes.add(&id, &candle);
+        self.candles_by_ids.insert(id, candle);
+    }
+
+    /// Inserts candle or returns error if a candle with the same id is already cached
+    pub fn try_insert(&mut self, candle: BidAskCandle) -> Result<(), CandleSdkError> {
+        let id = candle.get_id();
+
+        if self.candles_by_ids.contains_key(&id) {
+            return Err(CandleSdkError::DuplicateCandle { id });
+        }
+
+        self.insert(candle);
+
+        Ok(())
+    }
+
+    /// Gets count of candles sharing instrument, candle type and date with another candle,
+    /// not counting the first one of each group. Candles are keyed by ids derived from these
+    /// fields and inserting a candle with a cached id overwrites the cached candle,
+    /// so it's always 0 until candles of several sources are merged
+    pub fn count_duplicates(&self) -> usize {
+        let unique_keys: AHashSet<(&str, &CandleType, DateTime<Utc>)> = self
+            .candles_by_ids
+            .values()
+            .map(|candle| (candle.instrument.as_str(), &candle.candle_type, candle.datetime))
+            .collect();
+
+        self.candles_by_ids.len() - unique_keys.len()
+    }
+
+    /// Gets id pairs of candles of the same instrument and candle type with overlapping periods,
+    /// e.g. after inserting candles not aligned to their type. Pairs are ordered by candle dates
+    pub fn find_overlaps(&self) -> Vec<(String, String)> {
+        let mut series_map: AHashMap<(&str, &CandleType), Vec<(&String, &BidAskCandle)>> = AHashMap::new();
+
+        for (id, candle) in self.candles_by_ids.iter() {
+            series_map
+                .entry((candle.instrument.as_str(), &candle.candle_type))
+                .or_default()
+                .push((id, candle));
+        }
+
+        let mut series_list: Vec<_> = series_map.into_iter().collect();
+        series_list.sort_by_key(|(key, _candles)| *key);
+        let mut overlaps = Vec::new();
+
+        for (_key, mut candles) in series_list {
+            candles.sort_by_key(|(id, candle)| (candle.datetime, *id));
+
+            for (index, (id, candle)) in candles.iter().enumerate() {
+                let end_date = candle.datetime + candle.candle_type.get_duration(candle.datetime);
+
+                for (next_id, _next_candle) in candles[index + 1..]
+                    .iter()
+                    .take_while(|(_next_id, next_candle)| next_candle.datetime < end_date)
+                {
+                    overlaps.push((id.to_string(), next_id.to_string()));
+                }
+            }
+        }
+
+        overlaps
+    }
+
+    /// Inserts candle or merges it into the existing candle with the same id
+    pub fn insert_merge(&mut self, candle: BidAskCandle) {
+        let id = candle.get_id();
+
+        if let Some(existing_candle) = self.candles_by_ids.get_mut(&id) {
+            existing_candle.merge(&candle);
+        } else {
+            self.insert(candle);
+        }
+    }
+
+    /// Inserts candles merging duplicate ids like `insert_merge`. Ids are computed and candles of
+    /// different ids are merged in parallel, while candles of the same id are merged into the cached
+    /// candle in input order. So the result is bit-identical to sequential `insert_merge` calls,
+    /// except that with `max_capacity` new candles are inserted after all merges
+    #[cfg(feature = "rayon")]
+    pub fn par_extend(&mut self, candles: Vec<BidAskCandle>) {
+        use rayon::prelude::*;
+
+        let ids: Vec<String> = candles.par_iter().map(|candle| candle.get_id()).collect();
+        let mut group_indexes: AHashMap<String, usize> = AHashMap::with_capacity(ids.len());
+        let mut groups: Vec<(String, Vec<BidAskCandle>)> = Vec::new();
+
+        for (id, candle) in ids.into_iter().zip(candles) {
+            match group_indexes.get(&id) {
+                Some(index) => groups[*index].1.push(candle),
+                None => {
+                    group_indexes.insert(id.clone(), groups.len());
+                    groups.push((id, vec![candle]));
+                }
+            }
+        }
+
+        let candles_by_ids = &self.candles_by_ids;
+        let merged_candles: Vec<(String, BidAskCandle)> = groups
+            .into_par_iter()
+            .map(|(id, group)| {
+                let mut group = group.into_iter();
+                let mut merged_candle = match candles_by_ids.get(&id) {
+                    Some(cached_candle) => cached_candle.clone(),
+                    None => group.next().expect("group is not empty"),
+                };
+
+                for candle in group {
+                    merged_candle.merge(&candle);
+                }
+
+                (id, merged_candle)
+            })
+            .collect();
+
+        for (id, candle) in merged_candles {
+            match self.candles_by_ids.get_mut(&id) {
+                Some(cached_candle) => *cached_candle = candle,
+                None => self.insert(candle),
+            }
+        }
+    }
+
+    /// Replaces all cached candles with specified ones and rebuilds indexes
+    pub fn replace_contents(&mut self, candles: Vec<BidAskCandle>) {
+        self.candles_by_ids = candles
+            .into_iter()
+            .map(|candle| (candle.get_id(), candle))
+            .collect();
+        self.rebuild_indexes();
+    }
+
+    /// Releases excess capacity of the candles map and indexes, e.g. after removing many candles
+    pub fn shrink_to_fit(&mut self) {
+        self.candles_by_ids.shrink_to_fit();
+        self.indexes.shrink_to_fit();
+    }
+
+    /// Moves candles to a new map sized to the candles count, defragmenting the hash table
+    /// after many removals and insertions
+    pub fn compact(&mut self) {
+        let mut candles_by_ids = AHashMap::with_capacity(self.candles_by_ids.len());
+        candles_by_ids.extend(self.candles_by_ids.drain());
+        self.candles_by_ids = candles_by_ids;
+    }
+
+    /// Recomputes all secondary indexes from the cached candles
+    pub fn rebuild_indexes(&mut self) {
+        self.indexes.clear();
+
+        for (id, candle) in self.candles_by_ids.iter() {
+            self.indexes.add(id, candle);
+        }
+    }
+
+    pub fn get_by_instrument(&self, instrument: &str) -> Vec<&BidAskCandle> {
+        let Some(ids) = self.indexes.ids_by_instrument.get(instrument) else {
+            return vec![];
+        };
+
+        ids.iter()
+            .filter_map(|id| self.candles_by_ids.get(id))
+            .collect()
     }
 
     pub fn create_or_update(
@@ -56,73 +313,230 @@ impl CandlesCache {
         bid_vol: f64,
         ask_vol: f64,
     ) {
-        for candle_type in self.candle_types.iter() {
-            let candle_datetime = candle_type.get_start_date(datetime);
-            let id = BidAskCandle::generate_id(instrument, candle_type, candle_datetime);
-            let candle = self.candles_by_ids.get_mut(&id);
-
-            if let Some(candle) = candle {
-                candle.update(datetime, bid, ask, bid_vol, ask_vol);
-            } else {
+        if let Some(tolerance) = self.reject_future_ticks {
+            if datetime > Utc::now() + tolerance {
                 #[cfg(feature = "console-log")]
-                println!(
-                    "create candle {}: {} {}; {} total count",
-                    instrument.to_owned(),
-                    datetime.to_rfc3339(),
-                    id,
-                    self.candles_by_ids.len() + 1
-                );
-
-                self.candles_by_ids.insert(
-                    id,
-                    BidAskCandle {
-                        ask_data: CandleData::new(datetime, ask, ask_vol),
-                        bid_data: CandleData::new(datetime, bid, bid_vol),
-                        candle_type: candle_type.clone(),
-                        instrument: instrument.to_compact_string(),
-                        datetime: candle_datetime,
-                    },
-                );
-            }
-        }
-        
+                println!("reject future tick {}: {}", instrument, datetime.to_rfc3339());
+
+                return;
+            }
+        }
+
+        if self.reject_non_finite && [bid, ask, bid_vol, ask_vol].iter().any(|value| !value.is_finite()) {
+            #[cfg(feature = "console-log")]
+            println!("reject non finite tick {}: {}", instrument, datetime.to_rfc3339());
+
+            return;
+        }
+
+        match self.single_candle_type().cloned() {
+            // single type caches skip the candle types loop
+            Some(candle_type) => self.create_or_update_candle(&candle_type, datetime, instrument, (bid, bid_vol), (ask, ask_vol)),
+            None => {
+                let candle_types = std::mem::take(&mut self.candle_types);
+
+                for candle_type in candle_types.iter() {
+                    self.create_or_update_candle(candle_type, datetime, instrument, (bid, bid_vol), (ask, ask_vol));
+                }
+
+                self.candle_types = candle_types;
+            }
+        }
+
+        if !self.update_senders.is_empty() {
+            let instrument = instrument.to_compact_string();
+            let candle_types = &self.candle_types;
+
+            // dropped receivers unsubscribe, updates to full receivers are dropped
+            self.update_senders.retain(|sender| {
+                candle_types.iter().all(|candle_type| {
+                    !matches!(
+                        sender.try_send((instrument.clone(), candle_type.clone())),
+                        Err(TrySendError::Disconnected(_))
+                    )
+                })
+            });
+        }
+
         self.last_update_date.replace(Utc::now());
     }
 
-    /// Gets candles with date bigger or equals specified date
+    /// Gets receiver of instrument and candle type of every candle updated by `create_or_update`.
+    /// Receiver buffers up to `UPDATES_CAPACITY` updates, newer updates are dropped until it's read,
+    /// so a slow or idle subscriber doesn't grow memory or block ingestion.
+    /// Dropping the receiver unsubscribes it
+    pub fn subscribe_updates(&mut self) -> Receiver<(CompactString, CandleType)> {
+        let (sender, receiver) = sync_channel(UPDATES_CAPACITY);
+        self.update_senders.push(sender);
+
+        receiver
+    }
+
+    /// Applies tick bid and ask price with volume to the candle of the type
+    fn create_or_update_candle(
+        &mut self,
+        candle_type: &CandleType,
+        datetime: DateTime<Utc>,
+        instrument: &str,
+        (bid, bid_vol): (f64, f64),
+        (ask, ask_vol): (f64, f64),
+    ) {
+        let (candle_datetime, id) = self.get_tick_candle(instrument, candle_type, datetime);
+        let candle = self.candles_by_ids.get_mut(&id);
+
+        if let Some(candle) = candle {
+            candle.update(datetime, bid, ask, bid_vol, ask_vol);
+        } else {
+            #[cfg(feature = "console-log")]
+            println!(
+                "create candle {}: {} {}; {} total count",
+                instrument.to_owned(),
+                datetime.to_rfc3339(),
+                id,
+                self.candles_by_ids.len() + 1
+            );
+
+            let mut candle = BidAskCandle {
+                ask_data: CandleData::new(datetime, ask, ask_vol),
+                bid_data: CandleData::new(datetime, bid, bid_vol),
+                candle_type: candle_type.clone(),
+                instrument: instrument.to_compact_string(),
+                datetime: candle_datetime,
+                session: self.classify_session(candle_datetime),
+            };
+
+            if self.open_policy == OpenPolicy::PriorClose {
+                let prior_datetime = candle_type.get_prev_start_date(candle_datetime);
+                let prior_id = BidAskCandle::generate_id(instrument, candle_type, prior_datetime);
+
+                if let Some(prior_candle) = self.candles_by_ids.get(&prior_id) {
+                    candle.bid_data.set_open(prior_candle.bid_data.close);
+                    candle.ask_data.set_open(prior_candle.ask_data.close);
+                }
+            }
+
+            Self::ensure_capacity(&mut self.candles_by_ids, &mut self.indexes, self.max_capacity);
+            self.indexes.add(&id, &candle);
+            self.candles_by_ids.insert(id, candle);
+        }
+    }
+
+    /// Gets start date and id of the candle to update with the tick. A tick within `boundary_tolerance`
+    /// past a period start goes to the previous candle until a candle of the new period is created
+    fn get_tick_candle(&self, instrument: &str, candle_type: &CandleType, datetime: DateTime<Utc>) -> (DateTime<Utc>, String) {
+        let candle_datetime = candle_type.get_start_date(datetime);
+        let id = BidAskCandle::generate_id(instrument, candle_type, candle_datetime);
+
+        if datetime - candle_datetime >= self.boundary_tolerance || self.candles_by_ids.contains_key(&id) {
+            return (candle_datetime, id);
+        }
+
+        let prior_datetime = candle_type.get_prev_start_date(candle_datetime);
+        let prior_id = BidAskCandle::generate_id(instrument, candle_type, prior_datetime);
+
+        if self.candles_by_ids.contains_key(&prior_id) {
+            (prior_datetime, prior_id)
+        } else {
+            (candle_datetime, id)
+        }
+    }
+
+    fn classify_session(&self, candle_datetime: DateTime<Utc>) -> Session {
+        self.session_classifier
+            .as_ref()
+            .map(|classifier| classifier(candle_datetime))
+            .unwrap_or_default()
+    }
+
+    /// Gets candles of the session
+    pub fn get_by_session(&self, session: Session) -> Vec<&BidAskCandle> {
+        self.candles_by_ids
+            .values()
+            .filter(|candle| candle.session == session)
+            .collect()
+    }
+
+    /// Gets candle for the period of specified date or creates an empty one
+    pub fn get_mut_or_create(
+        &mut self,
+        datetime: DateTime<Utc>,
+        instrument: &str,
+        candle_type: &CandleType,
+    ) -> &mut BidAskCandle {
+        let candle_datetime = candle_type.get_start_date(datetime);
+        let id = BidAskCandle::generate_id(instrument, candle_type, candle_datetime);
+
+        if !self.candles_by_ids.contains_key(&id) {
+            Self::ensure_capacity(&mut self.candles_by_ids, &mut self.indexes, self.max_capacity);
+        }
+
+        let session = self.classify_session(candle_datetime);
+        let indexes = &mut self.indexes;
+
+        self.candles_by_ids.entry(id).or_insert_with_key(|id| {
+            let candle = BidAskCandle {
+                ask_data: CandleData::new_empty(candle_datetime),
+                bid_data: CandleData::new_empty(candle_datetime),
+                candle_type: candle_type.clone(),
+                instrument: instrument.to_compact_string(),
+                datetime: candle_datetime,
+                session,
+            };
+            indexes.add(id, &candle);
+
+            candle
+        })
+    }
+
+    /// Gets candles with date bigger or equals specified date ordered by instrument, candle type and date
     pub fn get_after(&self, datetime: DateTime<Utc>) -> Option<Vec<&BidAskCandle>> {
-        if self.candles_by_ids.len() == 0 {
+        if self.candles_by_ids.is_empty() {
             return None;
         }
 
-        let candle_dates = self.calculate_candle_dates(datetime);
+        let mut candles: Vec<&BidAskCandle> = match self.single_candle_type() {
+            // single type caches skip the dates map
+            Some(candle_type) => {
+                let current_date = candle_type.get_start_date(datetime);
 
-        let candles = self
-            .candles_by_ids
-            .iter()
-            .filter_map(|(_id, candle)| {
-                let current_date = candle_dates.get(&candle.candle_type).expect("wrong calculate_candle_dates");
+                self.candles_by_ids
+                    .values()
+                    .filter(|candle| candle.datetime >= current_date)
+                    .collect()
+            }
+            None => {
+                let candle_dates = self.calculate_candle_dates(datetime);
 
-                if candle.datetime >= *current_date {
-                    Some(candle)
-                } else {
-                    None
-                }
-            })
-            .collect();
+                self.candles_by_ids
+                    .values()
+                    .filter(|candle| {
+                        let current_date = candle_dates.get(&candle.candle_type).expect("wrong calculate_candle_dates");
+
+                        candle.datetime >= *current_date
+                    })
+                    .collect()
+            }
+        };
+        candles.sort_by(|a, b| {
+            (&a.instrument, &a.candle_type, a.datetime).cmp(&(&b.instrument, &b.candle_type, b.datetime))
+        });
 
         Some(candles)
     }
 
-    /// Removes candles with date less or equals specified date
+    /// Removes candles with date less or equals specified date. Without candle type
+    /// candles of types not tracked by the cache, e.g. inserted ones, are kept
     pub fn remove_before(&mut self, datetime: DateTime<Utc>, candle_type: Option<CandleType>) -> i32 {
         let mut removed_count = 0;
+        // single type caches skip the dates map
+        let candle_type = candle_type.or_else(|| self.single_candle_type().cloned());
 
         if let Some(candle_type) = candle_type {
-            self.candles_by_ids.retain(|_id, candle| {
-                let current_date = candle_type.get_start_date(datetime);
+            let current_date = candle_type.get_start_date(datetime);
 
+            self.candles_by_ids.retain(|id, candle| {
                 if candle.datetime <= current_date && candle.candle_type == candle_type {
+                    self.indexes.remove(id, candle);
                     removed_count += 1;
                     false
                 } else {
@@ -132,10 +546,13 @@ impl CandlesCache {
         } else {
             let dates = self.calculate_candle_dates(datetime);
 
-            self.candles_by_ids.retain(|_id, candle| {
-                let current_date = dates.get(&candle.candle_type).expect("Wrong calculate_candle_dates");
+            self.candles_by_ids.retain(|id, candle| {
+                let Some(current_date) = dates.get(&candle.candle_type) else {
+                    return true;
+                };
 
                 if candle.datetime <= *current_date {
+                    self.indexes.remove(id, candle);
                     removed_count += 1;
                     false
                 } else {
@@ -147,55 +564,1803 @@ impl CandlesCache {
         removed_count
     }
 
-    pub fn get(&self, id: &str) -> Option<&BidAskCandle> {
-        self.candles_by_ids.get(id)
+    /// Gets a new cache with copies of candles matching all specified filters.
+    /// `from` is inclusive and `to` is exclusive; `None` filters match any candle
+    pub fn filter_by(
+        &self,
+        instrument: Option<&str>,
+        candle_type: Option<&CandleType>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> CandlesCache {
+        let candles = self
+            .candles_by_ids
+            .values()
+            .filter(|candle| instrument.is_none_or(|instrument| candle.instrument == instrument))
+            .filter(|candle| candle_type.is_none_or(|candle_type| candle.candle_type == *candle_type))
+            .filter(|candle| from.is_none_or(|from| candle.datetime >= from))
+            .filter(|candle| to.is_none_or(|to| candle.datetime < to))
+            .cloned()
+            .collect();
+
+        let mut cache = CandlesCache::new(self.candle_types.clone());
+        cache.max_capacity = self.max_capacity;
+        cache.reject_future_ticks = self.reject_future_ticks;
+        cache.open_policy = self.open_policy;
+        cache.boundary_tolerance = self.boundary_tolerance;
+        cache.reject_non_finite = self.reject_non_finite;
+        cache.session_classifier = self.session_classifier.clone();
+        cache.replace_contents(candles);
+
+        cache
     }
 
-    fn calculate_candle_dates(&self, datetime: DateTime<Utc>) -> AHashMap<CandleType, DateTime<Utc>> {
-        let mut dates = AHashMap::with_capacity(self.candle_types.len());
+    /// Gets candles grouped by instrument in alphabetical order.
+    /// Group candles are ordered by candle type and date
+    pub fn iter_by_instrument(&self) -> impl Iterator<Item = (&str, Vec<&BidAskCandle>)> {
+        let mut instruments: Vec<&str> = self
+            .indexes
+            .ids_by_instrument
+            .keys()
+            .map(|instrument| instrument.as_str())
+            .collect();
+        instruments.sort();
 
-        for candle_type in self.candle_types.iter() {
-            dates.insert(candle_type.to_owned(), candle_type.get_start_date(datetime));
+        instruments.into_iter().map(|instrument| {
+            let mut candles = self.get_by_instrument(instrument);
+            candles.sort_by(|a, b| (&a.candle_type, a.datetime).cmp(&(&b.candle_type, b.datetime)));
+
+            (instrument, candles)
+        })
+    }
+
+    /// Gets the oldest and the newest candle dates of every cached candle type
+    pub fn spans_by_type(&self) -> AHashMap<CandleType, (DateTime<Utc>, DateTime<Utc>)> {
+        let mut spans: AHashMap<CandleType, (DateTime<Utc>, DateTime<Utc>)> = AHashMap::new();
+
+        for candle in self.candles_by_ids.values() {
+            let span = spans
+                .entry(candle.candle_type.clone())
+                .or_insert((candle.datetime, candle.datetime));
+            span.0 = span.0.min(candle.datetime);
+            span.1 = span.1.max(candle.datetime);
         }
 
-        dates
+        spans
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::models::candle_type::CandleType;
-    use chrono::{DateTime, TimeZone, Utc};
-    use crate::caches::candles_cache::CandlesCache;
+    /// Gets candles count of every cached candle type grouped by instrument
+    pub fn count_by_instrument_and_type(&self) -> AHashMap<String, AHashMap<CandleType, usize>> {
+        let mut counts: AHashMap<String, AHashMap<CandleType, usize>> = AHashMap::new();
 
-    #[tokio::test]
-    async fn calculate_candle_dates() {
-        let candle_types = [
-            CandleType::Minute,
-            CandleType::ThreeMinutes,
-            CandleType::FiveMinutes,
-            CandleType::FifteenMinutes,
-            CandleType::ThirtyMinutes,
-            CandleType::Hour,
-            CandleType::TwoHours,
-            CandleType::FourHours,
-            CandleType::SixHours,
-            CandleType::EightHours,
-            CandleType::TwelveHours,
-            CandleType::Day,
-            CandleType::ThreeDays,
-            CandleType::SevenDays,
-            CandleType::Month,
-        ];
-        let cache = CandlesCache::new(candle_types.to_vec());
-        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
-        let dates = cache.calculate_candle_dates(initial_date);
+        for candle in self.candles_by_ids.values() {
+            *counts
+                .entry(candle.instrument.to_string())
+                .or_default()
+                .entry(candle.candle_type.clone())
+                .or_insert(0) += 1;
+        }
 
-        assert_eq!(candle_types.len(), dates.len());
+        counts
+    }
 
-        for candle_type in candle_types.iter() {
-            let date = dates.get(&candle_type);
-            assert_eq!(date, Some(&candle_type.get_start_date(initial_date)))
+    /// Gets the latest dated candle of every cached candle type
+    pub fn get_newest_per_type(&self) -> AHashMap<CandleType, &BidAskCandle> {
+        let mut result: AHashMap<CandleType, &BidAskCandle> = AHashMap::new();
+
+        for candle in self.candles_by_ids.values() {
+            let newest_candle = result.entry(candle.candle_type.clone()).or_insert(candle);
+
+            if (candle.datetime, &candle.instrument) > (newest_candle.datetime, &newest_candle.instrument) {
+                *newest_candle = candle;
+            }
+        }
+
+        result
+    }
+
+    /// Gets the instrument candle of the current period for every configured candle type.
+    /// Types without a candle for the period are absent
+    pub fn instrument_snapshot(&self, instrument: &str, now: DateTime<Utc>) -> AHashMap<CandleType, &BidAskCandle> {
+        self.candle_types
+            .iter()
+            .filter_map(|candle_type| {
+                let id = BidAskCandle::generate_id(instrument, candle_type, candle_type.get_start_date(now));
+
+                self.candles_by_ids
+                    .get(&id)
+                    .map(|candle| (candle_type.clone(), candle))
+            })
+            .collect()
+    }
+
+    /// Gets bid prices of all candles grouped by instrument and candle type.
+    /// Series are ordered by date and dated by period start
+    pub fn to_series_map(&self) -> AHashMap<(CompactString, CandleType), Vec<Ohlcv>> {
+        let mut series_map: AHashMap<(CompactString, CandleType), Vec<&BidAskCandle>> = AHashMap::new();
+
+        for candle in self.candles_by_ids.values() {
+            series_map
+                .entry((candle.instrument.clone(), candle.candle_type.clone()))
+                .or_default()
+                .push(candle);
+        }
+
+        series_map
+            .into_iter()
+            .map(|(key, mut candles)| {
+                candles.sort_by_key(|candle| candle.datetime);
+                let series = candles
+                    .into_iter()
+                    .map(|candle| Ohlcv {
+                        datetime: candle.datetime,
+                        ..Ohlcv::from(&candle.bid_data)
+                    })
+                    .collect();
+
+                (key, series)
+            })
+            .collect()
+    }
+
+    /// Gets candles of the instrument grouped by candle type. Series are ordered by date
+    pub fn instrument_series(&self, instrument: &str) -> AHashMap<CandleType, Vec<&BidAskCandle>> {
+        let mut series_map: AHashMap<CandleType, Vec<&BidAskCandle>> = AHashMap::new();
+
+        for candle in self.get_by_instrument(instrument) {
+            series_map.entry(candle.candle_type.clone()).or_default().push(candle);
+        }
+
+        for candles in series_map.values_mut() {
+            candles.sort_by_key(|candle| candle.datetime);
+        }
+
+        series_map
+    }
+
+    /// Converts candles of every instrument and candle type to a prices cache keyed by period start.
+    /// Uses bid data or, if `use_mid` is set, average of bid and ask data
+    pub fn to_price_caches(&self, use_mid: bool) -> AHashMap<(CompactString, CandleType), CandlePricesCache> {
+        let mut caches: AHashMap<(CompactString, CandleType), CandlePricesCache> = AHashMap::new();
+
+        for candle in self.candles_by_ids.values() {
+            let mut data = candle.bid_data.clone();
+
+            if use_mid {
+                data.open = (candle.bid_data.open + candle.ask_data.open) / 2.0;
+                data.close = (candle.bid_data.close + candle.ask_data.close) / 2.0;
+                data.high = (candle.bid_data.high + candle.ask_data.high) / 2.0;
+                data.low = (candle.bid_data.low + candle.ask_data.low) / 2.0;
+            }
+
+            caches
+                .entry((candle.instrument.clone(), candle.candle_type.clone()))
+                .or_insert_with(|| CandlePricesCache::new(candle.candle_type.clone()))
+                .prices_by_date
+                .insert(candle.datetime.timestamp(), data);
+        }
+
+        caches
+    }
+
+    /// Gets candles grouped by every configured candle type, including types without candles.
+    /// Group candles are ordered by instrument and date
+    pub fn iter_by_type(&self) -> impl Iterator<Item = (CandleType, Vec<&BidAskCandle>)> {
+        self.candle_types.iter().map(|candle_type| {
+            let mut candles: Vec<&BidAskCandle> = self
+                .indexes
+                .ids_by_type
+                .get(candle_type)
+                .map(|ids| ids.iter().filter_map(|id| self.candles_by_ids.get(id)).collect())
+                .unwrap_or_default();
+            candles.sort_by(|a, b| (&a.instrument, a.datetime).cmp(&(&b.instrument, b.datetime)));
+
+            (candle_type.clone(), candles)
+        })
+    }
+
+    /// Removes all candles of the instrument. Returns removed count
+    pub fn remove_by_instrument(&mut self, instrument: &str) -> usize {
+        let Some(ids) = self.indexes.ids_by_instrument.get(instrument).cloned() else {
+            return 0;
+        };
+
+        for id in ids.iter() {
+            if let Some(candle) = self.candles_by_ids.remove(id) {
+                self.indexes.remove(id, &candle);
+            }
+        }
+
+        ids.len()
+    }
+
+    /// Multiplies bid and ask prices of the instrument candles started before specified date by factor.
+    /// Used to adjust history for splits and dividends
+    pub fn apply_price_adjustment(&mut self, instrument: &str, factor: f64, before: DateTime<Utc>) {
+        let Some(ids) = self.indexes.ids_by_instrument.get(instrument) else {
+            return;
+        };
+
+        for id in ids.iter() {
+            if let Some(candle) = self.candles_by_ids.get_mut(id) {
+                if candle.datetime < before {
+                    candle.bid_data.scale_prices(factor);
+                    candle.ask_data.scale_prices(factor);
+                }
+            }
+        }
+    }
+
+    /// Moves all candles of the instrument to the new instrument name updating their ids.
+    /// Candles already cached under the new name are replaced. Returns renamed count
+    pub fn rename_instrument(&mut self, old_name: &str, new_name: &str) -> usize {
+        if old_name == new_name {
+            return 0;
+        }
+
+        let Some(ids) = self.indexes.ids_by_instrument.get(old_name).cloned() else {
+            return 0;
+        };
+
+        let mut candles = Vec::with_capacity(ids.len());
+
+        for id in ids.iter() {
+            if let Some(candle) = self.candles_by_ids.remove(id) {
+                self.indexes.remove(id, &candle);
+                candles.push(candle);
+            }
+        }
+
+        let count = candles.len();
+
+        for mut candle in candles {
+            candle.instrument = new_name.to_compact_string();
+            self.insert(candle);
+        }
+
+        count
+    }
+
+    /// Removes all candles of the candle type. Returns removed count
+    pub fn remove_by_candle_type(&mut self, candle_type: &CandleType) -> usize {
+        let Some(ids) = self.indexes.ids_by_type.get(candle_type).cloned() else {
+            return 0;
+        };
+
+        for id in ids.iter() {
+            if let Some(candle) = self.candles_by_ids.remove(id) {
+                self.indexes.remove(id, &candle);
+            }
+        }
+
+        ids.len()
+    }
+
+    /// Gets violations of bid and ask data invariants by candle id, ordered by id.
+    /// Empty when all candles are valid
+    pub fn validate_all(&self) -> Vec<(String, Vec<CandleValidationError>)> {
+        let mut result: Vec<(String, Vec<CandleValidationError>)> = self
+            .candles_by_ids
+            .iter()
+            .filter_map(|(id, candle)| {
+                let mut errors = candle.bid_data.validate().err().unwrap_or_default();
+
+                for error in candle.ask_data.validate().err().unwrap_or_default() {
+                    if !errors.contains(&error) {
+                        errors.push(error);
+                    }
+                }
+
+                if errors.is_empty() {
+                    None
+                } else {
+                    Some((id.clone(), errors))
+                }
+            })
+            .collect();
+        result.sort_by(|a, b| a.0.cmp(&b.0));
+
+        result
+    }
+
+    /// Gets missing candle start dates in range `[from, to)` for every cached instrument with gaps,
+    /// ordered by instrument
+    pub fn get_instruments_with_gap(
+        &self,
+        candle_type: &CandleType,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Vec<(String, Vec<DateTime<Utc>>)> {
+        let expected_dates: Vec<DateTime<Utc>> = candle_type
+            .get_start_dates(from, to)
+            .into_iter()
+            .filter(|date| *date < to)
+            .collect();
+        let mut instruments: Vec<&str> = self
+            .indexes
+            .ids_by_instrument
+            .keys()
+            .map(|instrument| instrument.as_str())
+            .collect();
+        instruments.sort();
+
+        instruments
+            .into_iter()
+            .filter_map(|instrument| {
+                let missing_dates: Vec<DateTime<Utc>> = expected_dates
+                    .iter()
+                    .filter(|date| {
+                        let id = BidAskCandle::generate_id(instrument, candle_type, **date);
+
+                        !self.candles_by_ids.contains_key(&id)
+                    })
+                    .cloned()
+                    .collect();
+
+                if missing_dates.is_empty() {
+                    None
+                } else {
+                    Some((instrument.to_string(), missing_dates))
+                }
+            })
+            .collect()
+    }
+
+    /// Gets expected instruments without any candle with date bigger or equals specified date
+    pub fn missing_instruments(&self, expected: &HashSet<&str>, since: DateTime<Utc>) -> Vec<String> {
+        let updated_instruments: AHashSet<&str> = self
+            .candles_by_ids
+            .values()
+            .filter(|candle| candle.datetime >= since)
+            .map(|candle| candle.instrument.as_str())
+            .collect();
+
+        let mut missing: Vec<String> = expected
+            .iter()
+            .filter(|instrument| !updated_instruments.contains(*instrument))
+            .map(|instrument| instrument.to_string())
+            .collect();
+        missing.sort();
+
+        missing
+    }
+
+    /// Compares candles of both caches by id and content
+    pub fn diff(&self, other: &CandlesCache) -> CacheDiff {
+        let mut diff = CacheDiff::default();
+
+        for (id, candle) in self.candles_by_ids.iter() {
+            match other.candles_by_ids.get(id) {
+                Some(other_candle) if other_candle != candle => diff.changed.push(id.to_owned()),
+                Some(_) => {}
+                None => diff.only_self.push(id.to_owned()),
+            }
+        }
+
+        diff.only_other = other
+            .candles_by_ids
+            .keys()
+            .filter(|id| !self.candles_by_ids.contains_key(*id))
+            .cloned()
+            .collect();
+
+        diff.only_self.sort();
+        diff.only_other.sort();
+        diff.changed.sort();
+
+        diff
+    }
+
+    /// Writes all candles ordered by id as csv with a header row. Fails with `InvalidInput`
+    /// before writing anything if an instrument contains a comma, quote or line break
+    pub fn export_to_csv(&self, mut writer: impl Write) -> std::io::Result<()> {
+        let invalid_instrument = self
+            .candles_by_ids
+            .values()
+            .find(|candle| candle.instrument.contains([',', '"', '\n', '\r']));
+
+        if let Some(candle) = invalid_instrument {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("instrument {:?} can't be written to csv", candle.instrument),
+            ));
+        }
+
+        let mut ids: Vec<&String> = self.candles_by_ids.keys().collect();
+        ids.sort();
+        writeln!(writer, "{}", CSV_HEADER)?;
+
+        for id in ids {
+            let candle = &self.candles_by_ids[id];
+            writeln!(
+                writer,
+                "{},{},{},{},{},{}",
+                candle.instrument,
+                candle.candle_type.to_owned() as i32,
+                candle.datetime.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+                format_csv_candle_data(&candle.bid_data),
+                format_csv_candle_data(&candle.ask_data),
+                candle.session.as_str(),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads candles from csv written by `export_to_csv`
+    pub fn import_from_csv(reader: impl BufRead, candle_types: Vec<CandleType>) -> Result<CandlesCache, ImportError> {
+        let mut cache = CandlesCache::new(candle_types);
+        let mut candles = Vec::new();
+
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line_no = i + 1;
+
+            if line.is_empty() || (line_no == 1 && line == CSV_HEADER) {
+                continue;
+            }
+
+            let values: Vec<&str> = line.split(',').collect();
+
+            if values.len() != CSV_COLUMNS_COUNT {
+                return Err(ImportError::MalformedRow {
+                    line: line_no,
+                    message: format!("expected {} columns, got {}", CSV_COLUMNS_COUNT, values.len()),
+                });
+            }
+
+            if values[0].is_empty() {
+                return Err(ImportError::UnknownInstrument { line: line_no });
+            }
+
+            let candle_type = values[1]
+                .parse::<i32>()
+                .ok()
+                .and_then(|value| CandleType::from_i32(value).ok())
+                .filter(|candle_type| cache.candle_types.contains(candle_type))
+                .ok_or_else(|| ImportError::UnknownCandleType {
+                    line: line_no,
+                    value: values[1].to_string(),
+                })?;
+
+            let session = Session::parse(values[19]).ok_or_else(|| ImportError::MalformedRow {
+                line: line_no,
+                message: format!("invalid session {}", values[19]),
+            })?;
+
+            candles.push(BidAskCandle {
+                candle_type,
+                datetime: parse_csv_datetime(values[2], line_no)?,
+                instrument: values[0].to_compact_string(),
+                bid_data: parse_csv_candle_data(&values[3..11], line_no)?,
+                ask_data: parse_csv_candle_data(&values[11..19], line_no)?,
+                session,
+            });
+        }
+
+        cache.replace_contents(candles);
+
+        Ok(cache)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&BidAskCandle> {
+        self.candles_by_ids.get(id)
+    }
+
+    /// Gets the candle of the same instrument and candle type for the preceding period
+    pub fn get_prev_candle(&self, candle: &BidAskCandle) -> Option<&BidAskCandle> {
+        let prev_datetime = candle.candle_type.get_prev_start_date(candle.datetime);
+        let prev_id = BidAskCandle::generate_id(&candle.instrument, &candle.candle_type, prev_datetime);
+
+        self.candles_by_ids.get(&prev_id)
+    }
+
+    /// Removes the oldest finished candles until there is room for one more candle.
+    /// The newest candle of a series is evicted only if all series have a single candle
+    fn ensure_capacity(
+        candles_by_ids: &mut AHashMap<String, BidAskCandle>,
+        indexes: &mut CandleIndexes,
+        max_capacity: Option<usize>,
+    ) {
+        let Some(max_capacity) = max_capacity else {
+            return;
+        };
+
+        while candles_by_ids.len() >= max_capacity {
+            let Some(oldest_id) = indexes.get_eviction_id().cloned() else {
+                return;
+            };
+
+            #[cfg(feature = "console-log")]
+            println!("evict candle {}", oldest_id);
+
+            let Some(candle) = candles_by_ids.remove(&oldest_id) else {
+                indexes.remove_stale_id(&oldest_id);
+                continue;
+            };
+            indexes.remove(&oldest_id, &candle);
+        }
+    }
+
+    fn single_candle_type(&self) -> Option<&CandleType> {
+        match self.candle_types.as_slice() {
+            [candle_type] => Some(candle_type),
+            _ => None,
+        }
+    }
+
+    fn calculate_candle_dates(&self, datetime: DateTime<Utc>) -> AHashMap<CandleType, DateTime<Utc>> {
+        let mut dates = AHashMap::with_capacity(self.candle_types.len());
+
+        for candle_type in self.candle_types.iter() {
+            dates.insert(candle_type.to_owned(), candle_type.get_start_date(datetime));
+        }
+
+        dates
+    }
+}
+
+fn format_csv_candle_data(data: &CandleData) -> String {
+    format!(
+        "{},{},{},{},{},{},{},{}",
+        data.open,
+        data.close,
+        data.high,
+        data.low,
+        data.datetime.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        data.get_volume(),
+        data.tick_count,
+        data.sub_candle_count,
+    )
+}
+
+fn parse_csv_candle_data(values: &[&str], line: usize) -> Result<CandleData, ImportError> {
+    let parse_f64 = |value: &str| {
+        value.parse::<f64>().map_err(|_| ImportError::MalformedRow {
+            line,
+            message: format!("invalid number {}", value),
+        })
+    };
+
+    Ok(CandleData {
+        open: parse_f64(values[0])?,
+        close: parse_f64(values[1])?,
+        high: parse_f64(values[2])?,
+        low: parse_f64(values[3])?,
+        datetime: parse_csv_datetime(values[4], line)?,
+        volume: parse_f64(values[5])?,
+        tick_count: values[6].parse::<u64>().map_err(|_| ImportError::MalformedRow {
+            line,
+            message: format!("invalid tick count {}", values[6]),
+        })?,
+        sub_candle_count: values[7].parse::<u32>().map_err(|_| ImportError::MalformedRow {
+            line,
+            message: format!("invalid sub candle count {}", values[7]),
+        })?,
+        #[cfg(feature = "precise-volume")]
+        volume_compensation: 0.0,
+        #[cfg(feature = "record-history")]
+        history: Vec::new(),
+    })
+}
+
+fn parse_csv_datetime(value: &str, line: usize) -> Result<DateTime<Utc>, ImportError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|datetime| datetime.with_timezone(&Utc))
+        .map_err(|_| ImportError::InvalidDatetime {
+            line,
+            value: value.to_string(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{candle::BidAskCandle, candle_data::CandleData, candle_sdk_error::CandleSdkError, candle_type::CandleType, candle_validation_error::CandleValidationError, import_error::ImportError, open_policy::OpenPolicy, session::Session};
+    use ahash::AHashMap;
+    use chrono::{DateTime, Duration, TimeZone, Timelike, Utc};
+    use compact_str::CompactString;
+    use crate::caches::candles_cache::{CandlesCache, UPDATES_CAPACITY};
+    use std::collections::HashSet;
+
+    #[tokio::test]
+    async fn try_new_duplicates() {
+        let result = CandlesCache::try_new(vec![
+            CandleType::Hour,
+            CandleType::FifteenSeconds,
+            CandleType::Hour,
+            CandleType::Minute,
+            CandleType::Hour,
+            CandleType::FifteenSeconds,
+        ]);
+
+        let error = result.err().unwrap();
+        assert_eq!(
+            error,
+            CandleSdkError::DuplicateCandleTypes {
+                candle_types: vec![CandleType::Hour, CandleType::FifteenSeconds],
+            }
+        );
+        assert_eq!(error.to_string(), "Duplicate candle types: 1h, 15s");
+
+        let cache = CandlesCache::try_new(vec![CandleType::Minute, CandleType::FifteenSeconds]).unwrap();
+        assert_eq!(cache.candle_types.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn calculate_candle_dates() {
+        let candle_types = [
+            CandleType::FifteenSeconds,
+            CandleType::ThirtySeconds,
+            CandleType::Minute,
+            CandleType::TwoMinutes,
+            CandleType::ThreeMinutes,
+            CandleType::FiveMinutes,
+            CandleType::TenMinutes,
+            CandleType::FifteenMinutes,
+            CandleType::TwentyMinutes,
+            CandleType::ThirtyMinutes,
+            CandleType::FortyFiveMinutes,
+            CandleType::Hour,
+            CandleType::TwoHours,
+            CandleType::FourHours,
+            CandleType::SixHours,
+            CandleType::EightHours,
+            CandleType::TwelveHours,
+            CandleType::Day,
+            CandleType::ThreeDays,
+            CandleType::SevenDays,
+            CandleType::Monthly,
+        ];
+        let cache = CandlesCache::new(candle_types.to_vec());
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let dates = cache.calculate_candle_dates(initial_date);
+
+        assert_eq!(candle_types.len(), dates.len());
+
+        for candle_type in candle_types.iter() {
+            let date = dates.get(candle_type);
+            assert_eq!(date, Some(&candle_type.get_start_date(initial_date)))
+        }
+    }
+
+    #[tokio::test]
+    async fn missing_instruments() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        cache.create_or_update(initial_date, "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        cache.create_or_update(initial_date, "GBPUSD", 1.0, 1.1, 1.0, 1.0);
+        cache.create_or_update(initial_date + Duration::minutes(5), "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        cache.create_or_update(initial_date + Duration::minutes(5), "USDJPY", 1.0, 1.1, 1.0, 1.0);
+        let expected: HashSet<&str> = ["EURUSD", "GBPUSD", "USDJPY"].into_iter().collect();
+
+        let missing = cache.missing_instruments(&expected, initial_date + Duration::minutes(1));
+
+        assert_eq!(missing, vec!["GBPUSD".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn get_mut_or_create() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        let candle = cache.get_mut_or_create(initial_date, "EURUSD", &CandleType::Minute);
+        candle.update(initial_date, 1.0, 1.1, 2.0, 3.0);
+        let candle = cache.get_mut_or_create(initial_date + Duration::seconds(30), "EURUSD", &CandleType::Minute);
+        candle.update(initial_date + Duration::seconds(30), 1.2, 1.3, 2.0, 3.0);
+
+        assert_eq!(cache.len(), 1);
+        let candle = cache.get_mut_or_create(initial_date, "EURUSD", &CandleType::Minute);
+        assert_eq!(candle.datetime, initial_date);
+        assert_eq!(candle.bid_data.open, 1.0);
+        assert_eq!(candle.bid_data.close, 1.2);
+        assert_eq!(candle.ask_data.volume, 6.0);
+        assert_eq!(candle.ask_data.tick_count, 2);
+    }
+
+    #[tokio::test]
+    async fn rebuild_indexes_after_bulk_load() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute, CandleType::Hour]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        cache.create_or_update(initial_date, "USDJPY", 1.0, 1.1, 1.0, 1.0);
+        let mut candles = Vec::new();
+
+        for i in 0..10 {
+            let instrument = if i % 2 == 0 { "EURUSD" } else { "GBPUSD" };
+            candles.push(create_candle(instrument, CandleType::Minute, initial_date + Duration::minutes(i)));
+        }
+
+        cache.replace_contents(candles);
+
+        assert_eq!(cache.len(), 10);
+        assert_eq!(cache.get_by_instrument("EURUSD").len(), 5);
+        assert_eq!(cache.get_by_instrument("GBPUSD").len(), 5);
+        assert!(cache.get_by_instrument("USDJPY").is_empty());
+        assert!(cache.get_by_instrument("EURUSD").iter().all(|candle| candle.instrument == "EURUSD"));
+        assert_eq!(cache.indexes.ids_by_type.get(&CandleType::Minute).map(|ids| ids.len()), Some(10));
+        assert!(cache.indexes.ids_by_type.get(&CandleType::Hour).is_none());
+    }
+
+    #[tokio::test]
+    async fn indexes_follow_removal() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        cache.create_or_update(initial_date, "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        cache.create_or_update(initial_date + Duration::minutes(1), "EURUSD", 1.0, 1.1, 1.0, 1.0);
+
+        cache.remove_before(initial_date, None);
+
+        assert_eq!(cache.get_by_instrument("EURUSD").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn csv_round_trip() {
+        let candle_types = vec![CandleType::Minute, CandleType::Hour];
+        let mut cache = CandlesCache::builder()
+            .candle_types(candle_types.clone())
+            .session_classifier(|datetime| match datetime.hour() {
+                0..=13 => Session::PreMarket,
+                14..=20 => Session::Regular,
+                _ => Session::AfterHours,
+            })
+            .build();
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in 0..25 {
+            let datetime = initial_date + Duration::milliseconds(i * 3_600_123);
+            let instrument = if i % 2 == 0 { "EURUSD" } else { "GBPUSD" };
+            cache.create_or_update(datetime, instrument, 1.0 + i as f64 * 0.001, 1.1, 0.3, 0.7);
+        }
+
+        assert_eq!(cache.len(), 50);
+        for candle in cache.candles_by_ids.values_mut().filter(|candle| candle.candle_type == CandleType::Hour) {
+            candle.bid_data.sub_candle_count = 30;
+            candle.ask_data.sub_candle_count = 30;
+        }
+        let mut csv = Vec::new();
+        cache.export_to_csv(&mut csv).unwrap();
+
+        let imported = CandlesCache::import_from_csv(csv.as_slice(), candle_types).unwrap();
+
+        // update history is not exported
+        #[cfg(feature = "record-history")]
+        for candle in cache.candles_by_ids.values_mut() {
+            candle.bid_data.history.clear();
+            candle.ask_data.history.clear();
+        }
+
+        assert_eq!(imported.get_all(), cache.get_all());
+        assert_eq!(imported.get_by_instrument("EURUSD").len(), cache.get_by_instrument("EURUSD").len());
+        assert_eq!(imported.get_by_session(Session::AfterHours).len(), 6);
+        let hour_candle = imported.get_all().values().find(|candle| candle.candle_type == CandleType::Hour).unwrap();
+        assert_eq!(hour_candle.completeness(&CandleType::Minute), 0.5);
+    }
+
+    #[cfg(feature = "precise-volume")]
+    #[tokio::test]
+    async fn csv_export_compensated_volume() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in 0..10 {
+            cache.create_or_update(initial_date + Duration::seconds(i), "EURUSD", 1.0, 1.1, 0.1, 0.1);
+        }
+
+        let mut csv = Vec::new();
+        cache.export_to_csv(&mut csv).unwrap();
+        let imported = CandlesCache::import_from_csv(csv.as_slice(), vec![CandleType::Minute]).unwrap();
+
+        let candle = imported.get_all().values().next().unwrap();
+        assert_eq!(candle.bid_data.get_volume(), 1.0);
+        assert_eq!(candle.ask_data.get_volume(), 1.0);
+    }
+
+    #[tokio::test]
+    async fn csv_export_invalid_instrument() {
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for instrument in ["EUR,USD", "EUR\nUSD", "EUR\"USD"] {
+            let mut cache = CandlesCache::new(vec![CandleType::Minute]);
+            cache.create_or_update(initial_date, "EURUSD", 1.0, 1.1, 1.0, 1.0);
+            cache.create_or_update(initial_date, instrument, 1.0, 1.1, 1.0, 1.0);
+            let mut csv = Vec::new();
+
+            let error = cache.export_to_csv(&mut csv).unwrap_err();
+
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+            assert!(csv.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn csv_import_errors() {
+        let header = "instrument,candle_type,datetime,bid_open,bid_close,bid_high,bid_low,bid_datetime,bid_volume,bid_tick_count,bid_sub_candle_count,ask_open,ask_close,ask_high,ask_low,ask_datetime,ask_volume,ask_tick_count,ask_sub_candle_count,session";
+        let data = "1,1,1,1,2000-01-01T00:00:00Z,1,1,0,1,1,1,1,2000-01-01T00:00:00Z,1,1,0,regular";
+
+        let result = CandlesCache::import_from_csv(format!("{}\nEURUSD,0", header).as_bytes(), vec![CandleType::Minute]);
+        assert!(matches!(result, Err(ImportError::MalformedRow { line: 2, .. })));
+
+        let result = CandlesCache::import_from_csv(format!(",0,2000-01-01T00:00:00Z,{}", data).as_bytes(), vec![CandleType::Minute]);
+        assert!(matches!(result, Err(ImportError::UnknownInstrument { line: 1 })));
+
+        let result = CandlesCache::import_from_csv(format!("EURUSD,1,2000-01-01T00:00:00Z,{}", data).as_bytes(), vec![CandleType::Minute]);
+        assert!(matches!(result, Err(ImportError::UnknownCandleType { line: 1, .. })));
+
+        let result = CandlesCache::import_from_csv(format!("EURUSD,0,2000-01-01,{}", data).as_bytes(), vec![CandleType::Minute]);
+        assert!(matches!(result, Err(ImportError::InvalidDatetime { line: 1, .. })));
+
+        let result = CandlesCache::import_from_csv(
+            format!("EURUSD,0,2000-01-01T00:00:00Z,{}", data.replace("regular", "closed")).as_bytes(),
+            vec![CandleType::Minute],
+        );
+        assert!(matches!(result, Err(ImportError::MalformedRow { line: 1, .. })));
+
+        let result = CandlesCache::import_from_csv(format!("EURUSD,0,2000-01-01T00:00:00Z,{}", data).as_bytes(), vec![CandleType::Minute]);
+        assert_eq!(result.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn insert_merge() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let mut candle = create_candle("EURUSD", CandleType::Minute, initial_date);
+        candle.update(initial_date + Duration::seconds(10), 1.5, 1.6, 2.0, 2.0);
+        let mut other = create_candle("EURUSD", CandleType::Minute, initial_date);
+        other.update(initial_date + Duration::seconds(20), 0.5, 0.6, 3.0, 3.0);
+        other.update(initial_date + Duration::seconds(30), 0.8, 0.9, 1.0, 1.0);
+        let id = candle.get_id();
+
+        cache.insert(candle);
+        cache.insert_merge(other);
+
+        assert_eq!(cache.len(), 1);
+        let merged = cache.get(&id).unwrap();
+        assert_eq!(merged.bid_data.open, 1.0);
+        assert_eq!(merged.bid_data.high, 1.5);
+        assert_eq!(merged.bid_data.low, 0.5);
+        assert_eq!(merged.bid_data.close, 0.8);
+        assert_eq!(merged.bid_data.volume, 8.0);
+        assert_eq!(merged.ask_data.high, 1.6);
+        assert_eq!(merged.ask_data.low, 0.6);
+    }
+
+    #[tokio::test]
+    async fn filter_by() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute, CandleType::Hour]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in 0..10 {
+            let datetime = initial_date + Duration::minutes(i * 20);
+            cache.create_or_update(datetime, "EURUSD", 1.0, 1.1, 1.0, 1.0);
+            cache.create_or_update(datetime, "GBPUSD", 1.0, 1.1, 1.0, 1.0);
+        }
+
+        let mut filtered = cache.filter_by(
+            Some("EURUSD"),
+            Some(&CandleType::Minute),
+            Some(initial_date + Duration::minutes(40)),
+            Some(initial_date + Duration::minutes(100)),
+        );
+
+        assert_eq!(filtered.len(), 3);
+        assert!(filtered.get_all().values().all(|candle| candle.instrument == "EURUSD"
+            && candle.candle_type == CandleType::Minute
+            && candle.datetime >= initial_date + Duration::minutes(40)
+            && candle.datetime < initial_date + Duration::minutes(100)));
+        assert_eq!(cache.filter_by(None, Some(&CandleType::Hour), None, None).len(), 8);
+        assert_eq!(cache.filter_by(None, None, None, None).len(), cache.len());
+
+        filtered.remove_before(initial_date + Duration::days(1), None);
+        assert!(filtered.is_empty());
+        assert_eq!(cache.len(), 28);
+    }
+
+    #[tokio::test]
+    async fn instruments_sorted() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for instrument in ["USDJPY", "GBPUSD", "EURUSD", "AUDUSD"] {
+            cache.create_or_update(initial_date, instrument, 1.0, 1.1, 1.0, 1.0);
+        }
+
+        assert_eq!(cache.instruments_sorted(), vec!["AUDUSD", "EURUSD", "GBPUSD", "USDJPY"]);
+        assert!(CandlesCache::new(vec![CandleType::Minute]).instruments_sorted().is_empty());
+    }
+
+    #[tokio::test]
+    async fn instruments_sorted_unique() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute, CandleType::Hour, CandleType::Day]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in 0..3 {
+            cache.create_or_update(initial_date + Duration::hours(i), "GBPUSD", 1.0, 1.1, 1.0, 1.0);
+            cache.create_or_update(initial_date + Duration::hours(i), "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        }
+
+        assert_eq!(cache.len(), 14);
+        assert_eq!(cache.instruments_sorted(), vec!["EURUSD", "GBPUSD"]);
+    }
+
+    #[tokio::test]
+    async fn total_instruments() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute, CandleType::Hour]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let instruments = ["EURUSD", "GBPUSD", "USDJPY", "AUDUSD", "USDCAD"];
+
+        for (i, instrument) in instruments.iter().enumerate() {
+            cache.create_or_update(initial_date + Duration::minutes(i as i64), instrument, 1.0, 1.1, 1.0, 1.0);
+            cache.create_or_update(initial_date + Duration::minutes(10), instrument, 1.0, 1.1, 1.0, 1.0);
+        }
+
+        assert_eq!(cache.total_instruments(), 5);
+
+        let removed = cache.remove_by_instrument("USDJPY");
+
+        assert_eq!(removed, 3);
+        assert_eq!(cache.total_instruments(), 4);
+        assert!(cache.get_by_instrument("USDJPY").is_empty());
+        assert_eq!(cache.len(), 12);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[tokio::test]
+    async fn par_extend() {
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let mut candles = Vec::new();
+
+        for i in 0..1000 {
+            let instrument = if i % 3 == 0 { "EURUSD" } else { "GBPUSD" };
+            let datetime = initial_date + Duration::minutes(i % 50);
+            let volume = if i % 2 == 0 { 0.1 } else { 0.7 };
+            let mut candle = create_candle(instrument, CandleType::Minute, datetime);
+            candle.update(datetime + Duration::seconds(i % 60), 1.0 + i as f64 * 0.5, 1.1, volume, volume * 3.0);
+            candles.push(candle);
+        }
+
+        let mut sequential = CandlesCache::new(vec![CandleType::Minute]);
+        let mut parallel = CandlesCache::new(vec![CandleType::Minute]);
+
+        for i in 0..10 {
+            let mut candle = create_candle("EURUSD", CandleType::Minute, initial_date + Duration::minutes(i));
+            candle.update(initial_date + Duration::minutes(i), 1.3, 1.4, 0.3, 0.1);
+            sequential.insert(candle.clone());
+            parallel.insert(candle);
+        }
+
+        for candle in candles.iter() {
+            sequential.insert_merge(candle.clone());
+        }
+
+        parallel.par_extend(candles);
+
+        assert_eq!(parallel.len(), 100);
+        assert_eq!(parallel.get_all(), sequential.get_all());
+        assert_eq!(parallel.total_instruments(), 2);
+    }
+
+    #[tokio::test]
+    async fn get_instruments_with_gap() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in 0..10 {
+            let datetime = initial_date + Duration::minutes(i);
+            cache.create_or_update(datetime, "EURUSD", 1.0, 1.1, 1.0, 1.0);
+
+            if i != 3 && i != 7 {
+                cache.create_or_update(datetime, "GBPUSD", 1.0, 1.1, 1.0, 1.0);
+            }
+        }
+
+        let gaps = cache.get_instruments_with_gap(&CandleType::Minute, initial_date, initial_date + Duration::minutes(10));
+
+        assert_eq!(
+            gaps,
+            vec![(
+                "GBPUSD".to_string(),
+                vec![initial_date + Duration::minutes(3), initial_date + Duration::minutes(7)]
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn iter_by_instrument() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute, CandleType::Hour]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for (i, instrument) in ["USDJPY", "EURUSD", "GBPUSD"].iter().enumerate() {
+            for minute in 0..=i as i64 {
+                cache.create_or_update(initial_date + Duration::minutes(minute), instrument, 1.0, 1.1, 1.0, 1.0);
+            }
+        }
+
+        let groups: Vec<(&str, Vec<&BidAskCandle>)> = cache.iter_by_instrument().collect();
+
+        assert_eq!(groups.len(), cache.total_instruments());
+        assert_eq!(groups.iter().map(|(_instrument, candles)| candles.len()).sum::<usize>(), cache.len());
+        let instruments: Vec<&str> = groups.iter().map(|(instrument, _candles)| *instrument).collect();
+        assert_eq!(instruments, vec!["EURUSD", "GBPUSD", "USDJPY"]);
+        assert!(groups.iter().all(|(instrument, candles)| candles.iter().all(|candle| candle.instrument == *instrument)));
+    }
+
+    #[tokio::test]
+    async fn diff() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in 0..3 {
+            cache.insert(create_candle("EURUSD", CandleType::Minute, initial_date + Duration::minutes(i)));
+        }
+
+        let mut other = cache.filter_by(None, None, None, None);
+        let removed = create_candle("EURUSD", CandleType::Minute, initial_date);
+        let added = create_candle("EURUSD", CandleType::Minute, initial_date + Duration::minutes(3));
+        let changed_date = initial_date + Duration::minutes(1);
+        other.replace_contents(other.get_all().values().filter(|candle| **candle != removed).cloned().collect());
+        other.insert(added.clone());
+        other.get_mut_or_create(changed_date, "EURUSD", &CandleType::Minute).update(changed_date, 2.0, 2.1, 1.0, 1.0);
+
+        let diff = cache.diff(&other);
+
+        assert_eq!(diff.only_self, vec![removed.get_id()]);
+        assert_eq!(diff.only_other, vec![added.get_id()]);
+        assert_eq!(diff.changed, vec![BidAskCandle::generate_id("EURUSD", &CandleType::Minute, changed_date)]);
+        assert!(cache.diff(&cache).is_empty());
+    }
+
+    #[tokio::test]
+    async fn apply_price_adjustment() {
+        let mut cache = CandlesCache::new(vec![CandleType::Hour]);
+        let split_date = Utc.with_ymd_and_hms(2000, 1, 1, 2, 0, 0).unwrap();
+
+        for hour in 0..4 {
+            let datetime = Utc.with_ymd_and_hms(2000, 1, 1, hour, 0, 0).unwrap();
+            cache.insert(create_candle("EURUSD", CandleType::Hour, datetime));
+            cache.insert(create_candle("GBPUSD", CandleType::Hour, datetime));
+        }
+
+        cache.apply_price_adjustment("EURUSD", 2.0, split_date);
+
+        for candle in cache.get_by_instrument("EURUSD") {
+            let expected_factor = if candle.datetime < split_date { 2.0 } else { 1.0 };
+
+            for (data, price) in [(&candle.bid_data, 1.0), (&candle.ask_data, 1.1)] {
+                assert_eq!(data.open, price * expected_factor);
+                assert_eq!(data.close, price * expected_factor);
+                assert_eq!(data.high, price * expected_factor);
+                assert_eq!(data.low, price * expected_factor);
+            }
+        }
+
+        for candle in cache.get_by_instrument("GBPUSD") {
+            assert_eq!(candle.bid_data.open, 1.0);
+            assert_eq!(candle.ask_data.open, 1.1);
+        }
+    }
+
+    #[tokio::test]
+    async fn reject_future_ticks() {
+        let mut cache = CandlesCache::builder()
+            .candle_types(vec![CandleType::Minute])
+            .reject_future_ticks(Duration::minutes(5))
+            .build();
+
+        cache.create_or_update(Utc::now() + Duration::days(365), "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        assert!(cache.is_empty());
+        assert_eq!(cache.last_update_date, None);
+
+        cache.create_or_update(Utc::now() + Duration::minutes(1), "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn get_prev_candle() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute, CandleType::Hour]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        cache.create_or_update(initial_date, "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        cache.create_or_update(initial_date + Duration::seconds(70), "EURUSD", 1.2, 1.3, 1.0, 1.0);
+        cache.create_or_update(initial_date + Duration::seconds(70), "GBPUSD", 1.2, 1.3, 1.0, 1.0);
+
+        let first = cache.get(&BidAskCandle::generate_id("EURUSD", &CandleType::Minute, initial_date)).unwrap();
+        let second = cache.get(&BidAskCandle::generate_id("EURUSD", &CandleType::Minute, initial_date + Duration::minutes(1))).unwrap();
+        let hour = cache.get(&BidAskCandle::generate_id("EURUSD", &CandleType::Hour, initial_date)).unwrap();
+        let other = cache.get(&BidAskCandle::generate_id("GBPUSD", &CandleType::Minute, initial_date + Duration::minutes(1))).unwrap();
+
+        assert_eq!(cache.get_prev_candle(second), Some(first));
+        assert_eq!(cache.get_prev_candle(first), None);
+        assert_eq!(cache.get_prev_candle(hour), None);
+        assert_eq!(cache.get_prev_candle(other), None);
+    }
+
+    #[tokio::test]
+    async fn instrument_snapshot() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute, CandleType::Hour, CandleType::Day]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        cache.create_or_update(initial_date, "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        cache.create_or_update(initial_date + Duration::minutes(90), "EURUSD", 2.0, 2.1, 1.0, 1.0);
+        cache.create_or_update(initial_date + Duration::minutes(90), "GBPUSD", 3.0, 3.1, 1.0, 1.0);
+
+        let now = initial_date + Duration::seconds(90 * 60 + 30);
+        let snapshot = cache.instrument_snapshot("EURUSD", now);
+
+        assert_eq!(snapshot.len(), 3);
+
+        for candle_type in [CandleType::Minute, CandleType::Hour, CandleType::Day] {
+            let candle = snapshot.get(&candle_type).unwrap();
+            assert_eq!(candle.instrument, "EURUSD");
+            assert_eq!(candle.datetime, candle_type.get_start_date(now));
+            assert_eq!(candle.bid_data.close, 2.0);
+        }
+
+        let snapshot = cache.instrument_snapshot("EURUSD", now + Duration::minutes(1));
+
+        assert_eq!(snapshot.len(), 2);
+        assert!(!snapshot.contains_key(&CandleType::Minute));
+        assert!(cache.instrument_snapshot("USDJPY", now).is_empty());
+    }
+
+    #[tokio::test]
+    async fn remove_by_candle_type() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute, CandleType::Hour, CandleType::Day]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in 0..3 {
+            cache.create_or_update(initial_date + Duration::minutes(i), "EURUSD", 1.0, 1.1, 1.0, 1.0);
+            cache.create_or_update(initial_date + Duration::minutes(i), "GBPUSD", 1.0, 1.1, 1.0, 1.0);
+        }
+
+        assert_eq!(cache.len(), 10);
+        assert_eq!(cache.remove_by_candle_type(&CandleType::Minute), 6);
+        assert_eq!(cache.len(), 4);
+        assert!(cache.get_all().values().all(|candle| candle.candle_type != CandleType::Minute));
+        assert_eq!(cache.get_by_instrument("EURUSD").len(), 2);
+        assert_eq!(cache.remove_by_candle_type(&CandleType::Minute), 0);
+    }
+
+    #[tokio::test]
+    async fn open_policy_prior_close() {
+        let mut cache = CandlesCache::builder()
+            .candle_types(vec![CandleType::Minute])
+            .open_policy(OpenPolicy::PriorClose)
+            .build();
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        cache.create_or_update(initial_date, "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        cache.create_or_update(initial_date + Duration::seconds(30), "EURUSD", 1.2, 1.3, 1.0, 1.0);
+        cache.create_or_update(initial_date + Duration::seconds(70), "EURUSD", 1.5, 1.6, 1.0, 1.0);
+
+        let id = BidAskCandle::generate_id("EURUSD", &CandleType::Minute, initial_date + Duration::minutes(1));
+        let candle = cache.get(&id).unwrap();
+
+        assert_eq!(candle.bid_data.open, 1.2);
+        assert_eq!(candle.ask_data.open, 1.3);
+        assert_eq!(candle.bid_data.low, 1.2);
+        assert_eq!(candle.bid_data.close, 1.5);
+        assert_eq!(candle.bid_data.validate(), Ok(()));
+
+        let mut cache = CandlesCache::new(vec![CandleType::Minute]);
+        cache.create_or_update(initial_date, "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        cache.create_or_update(initial_date + Duration::seconds(70), "EURUSD", 1.5, 1.6, 1.0, 1.0);
+
+        assert_eq!(cache.get(&id).unwrap().bid_data.open, 1.5);
+    }
+
+    #[tokio::test]
+    async fn validate_all() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let mut invalid_candle = create_candle("GBPUSD", CandleType::Minute, initial_date);
+        invalid_candle.ask_data.high = 0.5;
+        invalid_candle.bid_data.volume = -1.0;
+        let invalid_id = invalid_candle.get_id();
+        cache.insert(create_candle("EURUSD", CandleType::Minute, initial_date));
+
+        assert!(cache.validate_all().is_empty());
+
+        cache.insert(invalid_candle);
+        let result = cache.validate_all();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].0, invalid_id);
+        assert!(result[0].1.contains(&CandleValidationError::NegativeVolume));
+        assert!(result[0].1.contains(&CandleValidationError::HighBelowLow));
+    }
+
+    #[tokio::test]
+    async fn contains_series() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute, CandleType::Hour]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        cache.create_or_update(initial_date, "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        cache.insert(create_candle("GBPUSD", CandleType::Day, initial_date));
+
+        assert!(cache.contains_series("EURUSD", &CandleType::Hour));
+        assert!(cache.contains_series("EURUSD", &CandleType::Minute));
+        assert!(cache.contains_series("GBPUSD", &CandleType::Day));
+        assert!(!cache.contains_series("EURUSD", &CandleType::Day));
+        assert!(!cache.contains_series("GBPUSD", &CandleType::Hour));
+        assert!(!cache.contains_series("USDJPY", &CandleType::Hour));
+    }
+
+    #[tokio::test]
+    async fn boundary_tolerance() {
+        let mut cache = CandlesCache::builder()
+            .candle_types(vec![CandleType::Hour])
+            .boundary_tolerance(Duration::milliseconds(5))
+            .build();
+        let boundary: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 1, 0, 0).unwrap();
+        cache.create_or_update(boundary - Duration::minutes(10), "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        cache.create_or_update(boundary + Duration::milliseconds(2), "EURUSD", 1.2, 1.3, 1.0, 1.0);
+
+        assert_eq!(cache.len(), 1);
+
+        let prior_id = BidAskCandle::generate_id("EURUSD", &CandleType::Hour, boundary - Duration::hours(1));
+
+        assert_eq!(cache.get(&prior_id).unwrap().bid_data.close, 1.2);
+
+        cache.create_or_update(boundary + Duration::milliseconds(10), "EURUSD", 1.4, 1.5, 1.0, 1.0);
+        cache.create_or_update(boundary + Duration::milliseconds(3), "EURUSD", 1.6, 1.7, 1.0, 1.0);
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&prior_id).unwrap().bid_data.close, 1.2);
+
+        let mut cache = CandlesCache::new(vec![CandleType::Hour]);
+        cache.create_or_update(boundary - Duration::minutes(10), "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        cache.create_or_update(boundary + Duration::milliseconds(2), "EURUSD", 1.2, 1.3, 1.0, 1.0);
+
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn to_series_map() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in (0..3).rev() {
+            cache.create_or_update(initial_date + Duration::seconds(i * 60 + 10), "EURUSD", 1.0 + i as f64, 1.1, 1.0, 1.0);
+            cache.create_or_update(initial_date + Duration::seconds(i * 60 + 10), "GBPUSD", 2.0 + i as f64, 2.1, 2.0, 1.0);
+        }
+
+        let series_map = cache.to_series_map();
+
+        assert_eq!(series_map.len(), 2);
+
+        let series = &series_map[&(CompactString::new("EURUSD"), CandleType::Minute)];
+
+        assert_eq!(series.iter().map(|ohlcv| ohlcv.datetime).collect::<Vec<_>>(), vec![initial_date, initial_date + Duration::minutes(1), initial_date + Duration::minutes(2)]);
+        assert_eq!(series.iter().map(|ohlcv| ohlcv.close).collect::<Vec<_>>(), vec![1.0, 2.0, 3.0]);
+
+        let series = &series_map[&(CompactString::new("GBPUSD"), CandleType::Minute)];
+
+        assert_eq!(series.iter().map(|ohlcv| ohlcv.open).collect::<Vec<_>>(), vec![2.0, 3.0, 4.0]);
+        assert!(series.iter().all(|ohlcv| ohlcv.volume == 2.0));
+    }
+
+    #[tokio::test]
+    async fn get_newest_per_type() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute, CandleType::Hour, CandleType::Day]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        cache.create_or_update(initial_date, "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        cache.create_or_update(initial_date + Duration::minutes(70), "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        cache.create_or_update(initial_date + Duration::minutes(30), "GBPUSD", 1.0, 1.1, 1.0, 1.0);
+
+        let newest = cache.get_newest_per_type();
+
+        assert_eq!(newest.len(), 3);
+        assert_eq!(newest[&CandleType::Minute].datetime, initial_date + Duration::minutes(70));
+        assert_eq!(newest[&CandleType::Minute].instrument, "EURUSD");
+        assert_eq!(newest[&CandleType::Hour].datetime, initial_date + Duration::hours(1));
+        assert_eq!(newest[&CandleType::Day].datetime, initial_date);
+        assert!(CandlesCache::new(vec![CandleType::Minute]).get_newest_per_type().is_empty());
+    }
+
+    #[tokio::test]
+    async fn get_after_sorted() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute, CandleType::Hour]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in (0..10).rev() {
+            cache.insert(create_candle("GBPUSD", CandleType::Minute, initial_date + Duration::minutes(i)));
+            cache.insert(create_candle("EURUSD", CandleType::Minute, initial_date + Duration::minutes(i)));
+            cache.insert(create_candle("EURUSD", CandleType::Hour, initial_date + Duration::hours(i)));
+        }
+
+        let candles = cache.get_after(initial_date + Duration::minutes(2)).unwrap();
+        let keys: Vec<(&str, CandleType, DateTime<Utc>)> = candles
+            .iter()
+            .map(|candle| (candle.instrument.as_str(), candle.candle_type.clone(), candle.datetime))
+            .collect();
+        let mut sorted_keys = keys.clone();
+        sorted_keys.sort();
+
+        assert_eq!(keys.len(), 8 + 8 + 10);
+        assert_eq!(keys, sorted_keys);
+        assert_eq!(keys[0], ("EURUSD", CandleType::Minute, initial_date + Duration::minutes(2)));
+    }
+
+    #[tokio::test]
+    async fn spans_by_type() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute, CandleType::Hour, CandleType::Day]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in [3, 1, 7, 5] {
+            cache.insert(create_candle("EURUSD", CandleType::Minute, initial_date + Duration::minutes(i)));
+            cache.insert(create_candle("GBPUSD", CandleType::Hour, initial_date + Duration::hours(i * 2)));
+        }
+
+        let spans = cache.spans_by_type();
+
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[&CandleType::Minute], (initial_date + Duration::minutes(1), initial_date + Duration::minutes(7)));
+        assert_eq!(spans[&CandleType::Hour], (initial_date + Duration::hours(2), initial_date + Duration::hours(14)));
+        assert!(!spans.contains_key(&CandleType::Day));
+    }
+
+    #[tokio::test]
+    async fn reject_non_finite() {
+        let mut cache = CandlesCache::builder()
+            .candle_types(vec![CandleType::Minute])
+            .reject_non_finite()
+            .build();
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        cache.create_or_update(initial_date, "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        cache.create_or_update(initial_date, "EURUSD", f64::NAN, 1.1, 1.0, 1.0);
+        cache.create_or_update(initial_date, "EURUSD", 1.0, 1.1, f64::INFINITY, 1.0);
+        cache.create_or_update(initial_date + Duration::minutes(1), "EURUSD", 1.0, f64::NAN, 1.0, 1.0);
+
+        assert_eq!(cache.len(), 1);
+        assert!(cache.validate_all().is_empty());
+
+        let candle = cache.get_all().values().next().unwrap();
+
+        assert_eq!(candle.bid_data.tick_count, 1);
+        assert_eq!(candle.bid_data.volume, 1.0);
+    }
+
+    #[tokio::test]
+    async fn max_capacity_replaced_unaligned_candle() {
+        let mut cache = CandlesCache::builder()
+            .candle_types(vec![CandleType::Minute])
+            .max_capacity(2)
+            .build();
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 10, 0, 0).unwrap();
+
+        cache.insert(create_candle("EURUSD", CandleType::Minute, initial_date + Duration::seconds(30)));
+        cache.insert(create_candle("EURUSD", CandleType::Minute, initial_date + Duration::seconds(45)));
+
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.indexes.ids_by_series.values().map(|ids| ids.len()).sum::<usize>(), 1);
+
+        for i in 1..5 {
+            cache.insert(create_candle("EURUSD", CandleType::Minute, initial_date + Duration::minutes(i)));
+            assert!(cache.len() <= 2);
+        }
+
+        // stale index entries are dropped instead of stopping eviction
+        cache.indexes.ids_by_series.values_mut().next().unwrap().insert(initial_date, "stale".to_string());
+        cache.insert(create_candle("EURUSD", CandleType::Minute, initial_date + Duration::minutes(5)));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.indexes.ids_by_series.values().all(|ids| ids.values().all(|id| cache.contains(id))));
+    }
+
+    #[tokio::test]
+    async fn try_insert_duplicate() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let candle = create_candle("EURUSD", CandleType::Minute, initial_date);
+        let mut other_candle = candle.clone();
+        other_candle.bid_data.close = 2.0;
+
+        assert_eq!(cache.try_insert(candle.clone()), Ok(()));
+        assert_eq!(
+            cache.try_insert(other_candle.clone()),
+            Err(CandleSdkError::DuplicateCandle { id: candle.get_id() })
+        );
+        assert_eq!(cache.get(&candle.get_id()).unwrap().bid_data.close, 1.0);
+        assert_eq!(cache.count_duplicates(), 0);
+
+        cache.insert(other_candle);
+
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&candle.get_id()).unwrap().bid_data.close, 2.0);
+        assert_eq!(cache.count_duplicates(), 0);
+    }
+
+    #[tokio::test]
+    async fn to_price_caches() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute, CandleType::Hour]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in 0..3 {
+            cache.create_or_update(initial_date + Duration::seconds(i * 60 + 10), "EURUSD", 1.0, 2.0, 1.0, 1.0);
+        }
+
+        cache.create_or_update(initial_date, "GBPUSD", 3.0, 3.2, 1.0, 1.0);
+
+        let caches = cache.to_price_caches(true);
+
+        assert_eq!(caches.len(), 4);
+
+        let minute_cache = &caches[&(CompactString::new("EURUSD"), CandleType::Minute)];
+
+        assert_eq!(minute_cache.candle_type, CandleType::Minute);
+        assert_eq!(minute_cache.len(), 3);
+        assert_eq!(minute_cache.get_at(initial_date + Duration::minutes(2)).unwrap().close, 1.5);
+
+        let hour_cache = &caches[&(CompactString::new("EURUSD"), CandleType::Hour)];
+
+        assert_eq!(hour_cache.candle_type, CandleType::Hour);
+        assert_eq!(hour_cache.len(), 1);
+        assert_eq!(hour_cache.get_at(initial_date).unwrap().tick_count, 3);
+
+        let caches = cache.to_price_caches(false);
+
+        assert_eq!(caches[&(CompactString::new("GBPUSD"), CandleType::Hour)].get_at(initial_date).unwrap().close, 3.0);
+    }
+
+    #[tokio::test]
+    async fn iter_by_type() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute, CandleType::Hour, CandleType::Day]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in (0..3).rev() {
+            cache.insert(create_candle("GBPUSD", CandleType::Minute, initial_date + Duration::minutes(i)));
+            cache.insert(create_candle("EURUSD", CandleType::Minute, initial_date + Duration::minutes(i)));
+            cache.insert(create_candle("EURUSD", CandleType::Hour, initial_date + Duration::hours(i)));
+        }
+
+        let groups: Vec<(CandleType, Vec<&BidAskCandle>)> = cache.iter_by_type().collect();
+
+        assert_eq!(
+            groups.iter().map(|(candle_type, _)| candle_type.clone()).collect::<Vec<_>>(),
+            vec![CandleType::Minute, CandleType::Hour, CandleType::Day]
+        );
+        assert_eq!(groups[0].1.len(), 6);
+        assert_eq!(groups[1].1.len(), 3);
+        assert!(groups[2].1.is_empty());
+
+        for (_, candles) in groups.iter() {
+            assert!(candles.windows(2).all(|pair| (&pair[0].instrument, pair[0].datetime) < (&pair[1].instrument, pair[1].datetime)));
+        }
+
+        assert_eq!(groups[0].1[0].instrument, "EURUSD");
+        assert_eq!(groups[0].1[3].instrument, "GBPUSD");
+    }
+
+    #[tokio::test]
+    async fn session_classifier() {
+        let mut cache = CandlesCache::builder()
+            .candle_types(vec![CandleType::Hour])
+            .session_classifier(|datetime| match datetime.hour() {
+                0..=13 => Session::PreMarket,
+                14..=20 => Session::Regular,
+                _ => Session::AfterHours,
+            })
+            .build();
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 3, 0, 0, 0).unwrap();
+
+        for hour in [9, 13, 14, 15, 20, 21] {
+            cache.create_or_update(initial_date + Duration::minutes(hour * 60 + 10), "AAPL", 1.0, 1.1, 1.0, 1.0);
+        }
+
+        cache.get_mut_or_create(initial_date + Duration::hours(22), "AAPL", &CandleType::Hour);
+
+        let sessions: Vec<(u32, Session)> = cache
+            .iter_by_type()
+            .flat_map(|(_, candles)| candles)
+            .map(|candle| (candle.datetime.hour(), candle.session))
+            .collect();
+
+        assert_eq!(
+            sessions,
+            vec![
+                (9, Session::PreMarket),
+                (13, Session::PreMarket),
+                (14, Session::Regular),
+                (15, Session::Regular),
+                (20, Session::Regular),
+                (21, Session::AfterHours),
+                (22, Session::AfterHours),
+            ]
+        );
+        assert_eq!(cache.get_by_session(Session::Regular).len(), 3);
+        assert!(CandlesCache::new(vec![CandleType::Hour]).get_by_session(Session::Regular).is_empty());
+    }
+
+    #[tokio::test]
+    async fn shrink_to_fit() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in 0..10_000 {
+            cache.create_or_update(initial_date + Duration::minutes(i), "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        }
+
+        cache.remove_before(initial_date + Duration::minutes(9_990), None);
+        let capacity = cache.candles_by_ids.capacity();
+        let index_capacity = cache.indexes.ids_by_type[&CandleType::Minute].capacity();
+
+        cache.shrink_to_fit();
+
+        assert_eq!(cache.len(), 9);
+        assert_eq!(cache.get_by_instrument("EURUSD").len(), 9);
+        assert!(cache.candles_by_ids.capacity() < capacity / 100);
+        assert!(cache.indexes.ids_by_type[&CandleType::Minute].capacity() < index_capacity / 100);
+    }
+
+    #[tokio::test]
+    async fn remove_before_keeps_untracked_types() {
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let after_date = initial_date + Duration::days(3);
+
+        for candle_types in [vec![CandleType::Minute], vec![CandleType::Minute, CandleType::Hour]] {
+            let mut cache = CandlesCache::new(candle_types);
+            cache.insert(create_candle("EURUSD", CandleType::Minute, initial_date));
+            cache.insert(create_candle("EURUSD", CandleType::Day, initial_date));
+
+            let removed = cache.remove_before(after_date, None);
+
+            assert_eq!(removed, 1);
+            assert_eq!(cache.len(), 1);
+            assert_eq!(cache.get_all().values().next().unwrap().candle_type, CandleType::Day);
+        }
+    }
+
+    #[tokio::test]
+    async fn single_type_matches_general() {
+        let build = |candle_types: Vec<CandleType>| {
+            CandlesCache::builder()
+                .candle_types(candle_types)
+                .open_policy(OpenPolicy::PriorClose)
+                .boundary_tolerance(Duration::seconds(5))
+                .build()
+        };
+        let mut single_type_cache = build(vec![CandleType::Minute]);
+        let mut general_cache = build(vec![CandleType::Minute, CandleType::Hour]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in 0..500 {
+            let datetime = initial_date + Duration::seconds(i * 17);
+            let instrument = if i % 3 == 0 { "EURUSD" } else { "GBPUSD" };
+            let bid = 1.0 + (i % 7) as f64 * 0.01;
+            single_type_cache.create_or_update(datetime, instrument, bid, bid + 0.1, 1.0, 2.0);
+            general_cache.create_or_update(datetime, instrument, bid, bid + 0.1, 1.0, 2.0);
+        }
+
+        let general_minute_candles: AHashMap<String, BidAskCandle> = general_cache
+            .get_all()
+            .iter()
+            .filter(|(_id, candle)| candle.candle_type == CandleType::Minute)
+            .map(|(id, candle)| (id.clone(), candle.clone()))
+            .collect();
+        assert_eq!(single_type_cache.get_all(), &general_minute_candles);
+
+        let minutes = |cache: &CandlesCache, datetime: DateTime<Utc>| -> Vec<BidAskCandle> {
+            cache
+                .get_after(datetime)
+                .unwrap()
+                .into_iter()
+                .filter(|candle| candle.candle_type == CandleType::Minute)
+                .cloned()
+                .collect()
+        };
+        let after_date = initial_date + Duration::seconds(2_000);
+        assert!(single_type_cache.single_candle_type().is_some());
+        assert!(general_cache.single_candle_type().is_none());
+        assert_eq!(minutes(&single_type_cache, after_date), minutes(&general_cache, after_date));
+
+        let removed = single_type_cache.remove_before(after_date, None);
+        general_cache.remove_before(after_date, None);
+
+        assert!(removed > 0);
+        assert_eq!(minutes(&single_type_cache, initial_date), minutes(&general_cache, initial_date));
+        let general_minutes_count = general_cache
+            .get_all()
+            .values()
+            .filter(|candle| candle.candle_type == CandleType::Minute)
+            .count();
+        assert_eq!(single_type_cache.len(), general_minutes_count);
+    }
+
+    #[tokio::test]
+    async fn find_overlaps() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute, CandleType::Hour]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 10, 0, 0).unwrap();
+
+        for i in 0..3 {
+            cache.insert(create_candle("EURUSD", CandleType::Hour, initial_date + Duration::hours(i)));
+            cache.insert(create_candle("EURUSD", CandleType::Minute, initial_date + Duration::minutes(i)));
+            cache.insert(create_candle("GBPUSD", CandleType::Hour, initial_date + Duration::hours(i)));
+        }
+
+        assert!(cache.find_overlaps().is_empty());
+
+        // replaces 10:00 candle keeping its id and overlaps 11:00 candle
+        let misaligned = create_candle("EURUSD", CandleType::Hour, initial_date + Duration::minutes(30));
+        cache.insert(misaligned.clone());
+
+        let overlaps = cache.find_overlaps();
+
+        let next_id = create_candle("EURUSD", CandleType::Hour, initial_date + Duration::hours(1)).get_id();
+        assert_eq!(cache.len(), 9);
+        assert_eq!(overlaps, vec![(misaligned.get_id(), next_id)]);
+    }
+
+    #[tokio::test]
+    async fn compact() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute, CandleType::Hour]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for cycle in 0..5 {
+            for i in 0..1_000 {
+                let datetime = initial_date + Duration::minutes(cycle * 1_000 + i);
+                cache.create_or_update(datetime, "EURUSD", 1.0, 1.1, 1.0, 1.0);
+            }
+
+            cache.remove_before(initial_date + Duration::minutes(cycle * 1_000 + 990), Some(CandleType::Minute));
+        }
+
+        let len = cache.len();
+        let candles = cache.get_all().clone();
+        let capacity = cache.candles_by_ids.capacity();
+
+        cache.compact();
+
+        assert_eq!(cache.len(), len);
+        assert!(cache.candles_by_ids.capacity() < capacity);
+        for (id, candle) in candles.iter() {
+            assert_eq!(cache.get(id), Some(candle));
+        }
+        assert_eq!(cache.get_by_instrument("EURUSD").len(), len);
+    }
+
+    #[tokio::test]
+    async fn rename_instrument() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in 0..3 {
+            cache.insert(create_candle("FB", CandleType::Minute, initial_date + Duration::minutes(i)));
+        }
+
+        cache.insert(create_candle("AAPL", CandleType::Minute, initial_date));
+
+        assert_eq!(cache.rename_instrument("FB", "META"), 3);
+        assert_eq!(cache.len(), 4);
+        assert!(cache.get_by_instrument("FB").is_empty());
+        assert_eq!(cache.get_by_instrument("META").len(), 3);
+        assert!(!cache.contains_series("FB", &CandleType::Minute));
+
+        for i in 0..3 {
+            let datetime = initial_date + Duration::minutes(i);
+
+            assert!(cache.get(&BidAskCandle::generate_id("META", &CandleType::Minute, datetime)).is_some());
+            assert!(cache.get(&BidAskCandle::generate_id("FB", &CandleType::Minute, datetime)).is_none());
+        }
+
+        assert_eq!(cache.rename_instrument("FB", "META"), 0);
+    }
+
+    #[tokio::test]
+    async fn subscribe_updates() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute, CandleType::Hour]);
+        let receiver = cache.subscribe_updates();
+        let dropped_receiver = cache.subscribe_updates();
+        drop(dropped_receiver);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        cache.create_or_update(initial_date, "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        cache.create_or_update(initial_date, "GBPUSD", 1.0, 1.1, 1.0, 1.0);
+
+        let updates: Vec<(CompactString, CandleType)> = receiver.try_iter().collect();
+
+        assert_eq!(
+            updates,
+            vec![
+                (CompactString::new("EURUSD"), CandleType::Minute),
+                (CompactString::new("EURUSD"), CandleType::Hour),
+                (CompactString::new("GBPUSD"), CandleType::Minute),
+                (CompactString::new("GBPUSD"), CandleType::Hour),
+            ]
+        );
+        assert_eq!(cache.update_senders.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn subscribe_updates_full_receiver() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute, CandleType::Hour]);
+        let receiver = cache.subscribe_updates();
+        let dropped_receiver = cache.subscribe_updates();
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in 0..UPDATES_CAPACITY as i64 {
+            cache.create_or_update(initial_date + Duration::seconds(i), "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        }
+
+        // full receivers stay subscribed, dropped ones are pruned on the next update
+        assert_eq!(cache.update_senders.len(), 2);
+        drop(dropped_receiver);
+        cache.create_or_update(initial_date, "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        assert_eq!(cache.update_senders.len(), 1);
+        assert_eq!(receiver.try_iter().count(), UPDATES_CAPACITY);
+
+        cache.create_or_update(initial_date, "GBPUSD", 1.0, 1.1, 1.0, 1.0);
+
+        let updates: Vec<(CompactString, CandleType)> = receiver.try_iter().collect();
+        assert_eq!(
+            updates,
+            vec![
+                (CompactString::new("GBPUSD"), CandleType::Minute),
+                (CompactString::new("GBPUSD"), CandleType::Hour),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn count_by_instrument_and_type() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute, CandleType::Hour, CandleType::Day]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let matrix = [
+            ("EURUSD", CandleType::Minute, 5),
+            ("EURUSD", CandleType::Hour, 3),
+            ("EURUSD", CandleType::Day, 2),
+            ("GBPUSD", CandleType::Minute, 4),
+            ("USDJPY", CandleType::Day, 1),
+        ];
+
+        for (instrument, candle_type, count) in matrix.iter() {
+            for i in 0..*count {
+                let datetime = initial_date + candle_type.get_duration(initial_date) * i;
+                cache.insert(create_candle(instrument, candle_type.clone(), datetime));
+            }
+        }
+
+        let counts = cache.count_by_instrument_and_type();
+        let mut expected: AHashMap<String, AHashMap<CandleType, usize>> = AHashMap::new();
+
+        for (instrument, candle_type, count) in matrix {
+            expected
+                .entry(instrument.to_string())
+                .or_default()
+                .insert(candle_type, count as usize);
+        }
+
+        assert_eq!(counts, expected);
+        assert_eq!(counts["GBPUSD"].len(), 1);
+        assert!(!counts["USDJPY"].contains_key(&CandleType::Minute));
+        assert!(CandlesCache::new(vec![CandleType::Minute]).count_by_instrument_and_type().is_empty());
+    }
+
+    #[tokio::test]
+    async fn instrument_series() {
+        let mut cache = CandlesCache::new(vec![CandleType::Minute, CandleType::Hour]);
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in [4, 1, 3, 0, 2] {
+            cache.insert(create_candle("EURUSD", CandleType::Minute, initial_date + Duration::minutes(i)));
+            cache.insert(create_candle("EURUSD", CandleType::Hour, initial_date + Duration::hours(i)));
+            cache.insert(create_candle("GBPUSD", CandleType::Minute, initial_date + Duration::minutes(i)));
+        }
+        cache.insert(create_candle("EURUSD", CandleType::Minute, initial_date + Duration::minutes(10)));
+
+        let series = cache.instrument_series("EURUSD");
+
+        assert_eq!(series.len(), 2);
+        let minutes: Vec<DateTime<Utc>> = series[&CandleType::Minute].iter().map(|candle| candle.datetime).collect();
+        let expected: Vec<DateTime<Utc>> = [0, 1, 2, 3, 4, 10].iter().map(|i| initial_date + Duration::minutes(*i)).collect();
+        assert_eq!(minutes, expected);
+        let hours: Vec<DateTime<Utc>> = series[&CandleType::Hour].iter().map(|candle| candle.datetime).collect();
+        assert_eq!(hours, (0..5).map(|i| initial_date + Duration::hours(i)).collect::<Vec<DateTime<Utc>>>());
+        assert!(series.values().flatten().all(|candle| candle.instrument == "EURUSD"));
+        assert!(cache.instrument_series("USDJPY").is_empty());
+    }
+
+    fn create_candle(instrument: &str, candle_type: CandleType, datetime: DateTime<Utc>) -> BidAskCandle {
+        BidAskCandle {
+            candle_type,
+            datetime,
+            instrument: CompactString::new(instrument),
+            bid_data: CandleData::new(datetime, 1.0, 1.0),
+            ask_data: CandleData::new(datetime, 1.1, 1.0),
+            session: Session::default(),
         }
     }
 }
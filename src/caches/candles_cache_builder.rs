@@ -0,0 +1,146 @@
+use crate::caches::candles_cache::CandlesCache;
+use crate::models::{candle_type::CandleType, open_policy::OpenPolicy, session::Session};
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Arc;
+
+#[derive(Default)]
+pub struct CandlesCacheBuilder {
+    candle_types: Vec<CandleType>,
+    max_capacity: Option<usize>,
+    reject_future_ticks: Option<Duration>,
+    open_policy: OpenPolicy,
+    boundary_tolerance: Duration,
+    reject_non_finite: bool,
+    session_classifier: Option<Arc<dyn Fn(DateTime<Utc>) -> Session + Send + Sync>>,
+}
+
+impl std::fmt::Debug for CandlesCacheBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CandlesCacheBuilder")
+            .field("candle_types", &self.candle_types)
+            .field("max_capacity", &self.max_capacity)
+            .field("reject_future_ticks", &self.reject_future_ticks)
+            .field("open_policy", &self.open_policy)
+            .field("boundary_tolerance", &self.boundary_tolerance)
+            .field("reject_non_finite", &self.reject_non_finite)
+            .field("session_classifier", &self.session_classifier.is_some())
+            .finish()
+    }
+}
+
+impl CandlesCacheBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn candle_types(mut self, types: Vec<CandleType>) -> Self {
+        self.candle_types = types;
+        self
+    }
+
+    /// Sets max count of stored candles. Panics if `n` is 0
+    pub fn max_capacity(mut self, n: usize) -> Self {
+        assert!(n > 0, "max_capacity must be positive");
+
+        self.max_capacity = Some(n);
+        self
+    }
+
+    pub fn reject_future_ticks(mut self, tolerance: Duration) -> Self {
+        self.reject_future_ticks = Some(tolerance);
+        self
+    }
+
+    pub fn open_policy(mut self, policy: OpenPolicy) -> Self {
+        self.open_policy = policy;
+        self
+    }
+
+    pub fn boundary_tolerance(mut self, tolerance: Duration) -> Self {
+        self.boundary_tolerance = tolerance;
+        self
+    }
+
+    pub fn reject_non_finite(mut self) -> Self {
+        self.reject_non_finite = true;
+        self
+    }
+
+    pub fn session_classifier(mut self, classifier: impl Fn(DateTime<Utc>) -> Session + Send + Sync + 'static) -> Self {
+        self.session_classifier = Some(Arc::new(classifier));
+        self
+    }
+
+    pub fn build(self) -> CandlesCache {
+        let mut cache = CandlesCache::new(self.candle_types);
+        cache.max_capacity = self.max_capacity;
+        cache.reject_future_ticks = self.reject_future_ticks;
+        cache.open_policy = self.open_policy;
+        cache.boundary_tolerance = self.boundary_tolerance;
+        cache.reject_non_finite = self.reject_non_finite;
+        cache.session_classifier = self.session_classifier;
+
+        cache
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::caches::candles_cache::CandlesCache;
+    use crate::models::candle_type::CandleType;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    #[tokio::test]
+    async fn build_with_max_capacity() {
+        let mut cache = CandlesCache::builder()
+            .candle_types(vec![CandleType::Minute])
+            .max_capacity(3)
+            .build();
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in 0..5 {
+            cache.create_or_update(initial_date + Duration::minutes(i), "EURUSD", 1.0, 1.1, 1.0, 1.0);
+        }
+
+        assert_eq!(cache.candle_types, vec![CandleType::Minute]);
+        assert_eq!(cache.len(), 3);
+        assert!(cache.get_after(initial_date).unwrap().iter().all(|candle| candle.datetime >= initial_date + Duration::minutes(2)));
+    }
+
+    #[tokio::test]
+    async fn max_capacity_keeps_live_candles() {
+        let mut cache = CandlesCache::builder()
+            .candle_types(vec![CandleType::Minute, CandleType::Day])
+            .max_capacity(4)
+            .build();
+        let initial_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for i in 0..10 {
+            cache.create_or_update(initial_date + Duration::minutes(i), "EURUSD", 1.0 + i as f64, 1.1 + i as f64, 1.0, 1.0);
+        }
+
+        let day_candle = cache.get_after(initial_date).unwrap()
+            .into_iter()
+            .find(|candle| candle.candle_type == CandleType::Day)
+            .cloned()
+            .unwrap();
+        assert_eq!(cache.len(), 4);
+        assert_eq!(day_candle.bid_data.open, 1.0);
+        assert_eq!(day_candle.bid_data.high, 10.0);
+        assert_eq!(day_candle.bid_data.volume, 10.0);
+        assert_eq!(day_candle.bid_data.tick_count, 10);
+        let mut minutes: Vec<DateTime<Utc>> = cache.get_after(initial_date).unwrap()
+            .into_iter()
+            .filter(|candle| candle.candle_type == CandleType::Minute)
+            .map(|candle| candle.datetime)
+            .collect();
+        minutes.sort();
+        assert_eq!(minutes, (7..10).map(|i| initial_date + Duration::minutes(i)).collect::<Vec<DateTime<Utc>>>());
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "max_capacity must be positive")]
+    async fn max_capacity_zero() {
+        CandlesCache::builder().max_capacity(0);
+    }
+}
@@ -1,2 +1,6 @@
 pub mod candle_prices_cache;
-pub mod candles_cache;
\ No newline at end of file
+pub mod candles_cache;
+pub mod candles_cache_builder;
+pub mod rolling_window;
+pub mod cache_diff;
+mod candle_indexes;
\ No newline at end of file
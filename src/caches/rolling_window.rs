@@ -0,0 +1,172 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::models::{candle_data::CandleData, candle_sdk_error::CandleSdkError};
+
+#[derive(Debug, Clone)]
+struct Tick {
+    no: u64,
+    datetime: DateTime<Utc>,
+    price: f64,
+    volume: f64,
+}
+
+/// Aggregates ticks of the last `window_secs` seconds ending at the latest tick.
+/// Unlike candles the window has no fixed boundaries
+#[derive(Debug, Clone)]
+pub struct RollingWindow {
+    window_secs: i64,
+    ticks: VecDeque<Tick>,
+    // candidates for high and low, monotonic by price
+    highs: VecDeque<(u64, f64)>,
+    lows: VecDeque<(u64, f64)>,
+    volume: f64,
+    next_tick_no: u64,
+}
+
+impl RollingWindow {
+    /// Creates window of positive length
+    pub fn new(window_secs: i64) -> Result<Self, CandleSdkError> {
+        if window_secs <= 0 {
+            return Err(CandleSdkError::InvalidWindow { window_secs });
+        }
+
+        Ok(Self {
+            window_secs,
+            ticks: VecDeque::new(),
+            highs: VecDeque::new(),
+            lows: VecDeque::new(),
+            volume: 0.0,
+            next_tick_no: 0,
+        })
+    }
+
+    pub fn window_secs(&self) -> i64 {
+        self.window_secs
+    }
+
+    /// Adds tick, removes ticks older than the window and returns the current aggregate
+    pub fn update(&mut self, datetime: DateTime<Utc>, price: f64, volume: f64) -> CandleData {
+        let no = self.next_tick_no;
+        self.next_tick_no += 1;
+
+        while self.highs.back().is_some_and(|(_no, high)| *high <= price) {
+            self.highs.pop_back();
+        }
+
+        while self.lows.back().is_some_and(|(_no, low)| *low >= price) {
+            self.lows.pop_back();
+        }
+
+        self.highs.push_back((no, price));
+        self.lows.push_back((no, price));
+        self.ticks.push_back(Tick {
+            no,
+            datetime,
+            price,
+            volume,
+        });
+        self.volume += volume;
+        self.evict(datetime);
+
+        self.get_current().expect("window contains the last tick")
+    }
+
+    pub fn get_current(&self) -> Option<CandleData> {
+        let first = self.ticks.front()?;
+        let last = self.ticks.back()?;
+
+        Some(CandleData {
+            open: first.price,
+            close: last.price,
+            high: self.highs.front()?.1,
+            low: self.lows.front()?.1,
+            datetime: last.datetime,
+            volume: self.volume,
+            tick_count: self.ticks.len() as u64,
+            sub_candle_count: 0,
+            #[cfg(feature = "precise-volume")]
+            volume_compensation: 0.0,
+            #[cfg(feature = "record-history")]
+            history: Vec::new(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.ticks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ticks.is_empty()
+    }
+
+    fn evict(&mut self, now: DateTime<Utc>) {
+        let window_start = now - Duration::seconds(self.window_secs);
+
+        while let Some(tick) = self.ticks.front() {
+            if tick.datetime >= window_start {
+                break;
+            }
+
+            let tick = self.ticks.pop_front().expect("front exists");
+            self.volume -= tick.volume;
+
+            if self.highs.front().is_some_and(|(no, _high)| *no == tick.no) {
+                self.highs.pop_front();
+            }
+
+            if self.lows.front().is_some_and(|(no, _low)| *no == tick.no) {
+                self.lows.pop_front();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::caches::rolling_window::RollingWindow;
+    use crate::models::candle_sdk_error::CandleSdkError;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    #[tokio::test]
+    async fn update_evicts_expired_ticks() {
+        let mut window = RollingWindow::new(10).unwrap();
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        window.update(from, 100.0, 1.0);
+        window.update(from + Duration::seconds(2), 150.0, 2.0);
+        window.update(from + Duration::seconds(4), 50.0, 3.0);
+        let candle = window.update(from + Duration::seconds(8), 90.0, 4.0);
+
+        assert_eq!(candle.open, 100.0);
+        assert_eq!(candle.high, 150.0);
+        assert_eq!(candle.low, 50.0);
+        assert_eq!(candle.close, 90.0);
+        assert_eq!(candle.volume, 10.0);
+
+        let candle = window.update(from + Duration::seconds(13), 95.0, 5.0);
+
+        assert_eq!(window.len(), 3);
+        assert_eq!(candle.open, 50.0);
+        assert_eq!(candle.high, 95.0);
+        assert_eq!(candle.low, 50.0);
+        assert_eq!(candle.volume, 12.0);
+
+        let candle = window.update(from + Duration::seconds(15), 92.0, 1.0);
+
+        assert_eq!(candle.open, 90.0);
+        assert_eq!(candle.high, 95.0);
+        assert_eq!(candle.low, 90.0);
+        assert_eq!(candle.close, 92.0);
+        assert_eq!(candle.volume, 10.0);
+        assert_eq!(candle.tick_count, 3);
+    }
+
+    #[tokio::test]
+    async fn new_invalid_window() {
+        assert!(matches!(RollingWindow::new(0), Err(CandleSdkError::InvalidWindow { window_secs: 0 })));
+        assert!(matches!(RollingWindow::new(-5), Err(CandleSdkError::InvalidWindow { window_secs: -5 })));
+        assert_eq!(RollingWindow::new(1).unwrap().window_secs(), 1);
+    }
+}
@@ -1,14 +1,15 @@
 use chrono::{DateTime, Utc};
 use compact_str::CompactString;
-use super::{candle_type::CandleType, candle_data::CandleData};
+use super::{candle_type::CandleType, candle_data::CandleData, session::Session};
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BidAskCandle {
     pub candle_type: CandleType,
     pub datetime: DateTime<Utc>,
     pub instrument: CompactString,
     pub bid_data: CandleData,
     pub ask_data: CandleData,
+    pub session: Session,
 }
 
 impl BidAskCandle {
@@ -17,6 +18,28 @@ impl BidAskCandle {
         self.ask_data.update(datetime, ask, ask_vol);
     }
 
+    pub fn merge(&mut self, other: &BidAskCandle) {
+        self.bid_data.merge(&other.bid_data);
+        self.ask_data.merge(&other.ask_data);
+    }
+
+    /// Gets share of base candles merged into this candle. See `CandleData::completeness`
+    pub fn completeness(&self, base: &CandleType) -> f64 {
+        self.bid_data.completeness(&self.candle_type, base)
+    }
+
+    /// Gets start date of the coarser candle containing this candle or `None` if the parent
+    /// type periods are not made of whole candles of this type
+    pub fn parent_start(&self, parent: CandleType) -> Option<DateTime<Utc>> {
+        if !parent.divisors().contains(&self.candle_type) {
+            return None;
+        }
+
+        Some(parent.get_start_date(self.datetime))
+    }
+
+    /// Gets `{type}{instrument}{timestamp}` id. Parts have no separators, so ids of different
+    /// candles collide if an instrument starts with a digit, e.g. type `1` with `0BTC` and type `10` with `BTC`
     pub fn generate_id(
         instrument: &str,
         candle_type: &CandleType,
@@ -25,7 +48,7 @@ impl BidAskCandle {
         format!(
             "{}{}{}",
             candle_type.to_owned() as u8,
-            instrument.to_string(),
+            instrument,
             candle_type.get_start_date(datetime).timestamp(),
         )
     }
@@ -33,4 +56,50 @@ impl BidAskCandle {
     pub fn get_id(&self) -> String {
         BidAskCandle::generate_id(&self.instrument, &self.candle_type, self.datetime)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{candle::BidAskCandle, candle_data::CandleData, candle_type::CandleType, session::Session};
+    use chrono::{DateTime, TimeZone, Utc};
+    use compact_str::CompactString;
+
+    #[tokio::test]
+    async fn parent_start() {
+        let datetime: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 14, 5, 0).unwrap();
+        let candle = BidAskCandle {
+            candle_type: CandleType::FiveMinutes,
+            datetime,
+            instrument: CompactString::new("EURUSD"),
+            bid_data: CandleData::new(datetime, 1.0, 1.0),
+            ask_data: CandleData::new(datetime, 1.1, 1.0),
+            session: Session::default(),
+        };
+
+        assert_eq!(candle.parent_start(CandleType::Hour), Some(Utc.with_ymd_and_hms(2000, 1, 1, 14, 0, 0).unwrap()));
+        assert_eq!(candle.parent_start(CandleType::Monthly), Some(Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap()));
+        assert_eq!(candle.parent_start(CandleType::FiveMinutes), None);
+        assert_eq!(candle.parent_start(CandleType::Minute), None);
+        assert_eq!(candle.parent_start(CandleType::ThreeMinutes), None);
+    }
+    #[tokio::test]
+    async fn completeness() {
+        let datetime: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 14, 0, 0).unwrap();
+        let mut candle = BidAskCandle {
+            candle_type: CandleType::Hour,
+            datetime,
+            instrument: CompactString::new("EURUSD"),
+            bid_data: CandleData::new(datetime, 1.0, 1.0),
+            ask_data: CandleData::new(datetime, 1.1, 1.0),
+            session: Session::default(),
+        };
+
+        assert_eq!(candle.completeness(&CandleType::Minute), 0.0);
+
+        candle.bid_data.sub_candle_count = 30;
+        candle.ask_data.sub_candle_count = 30;
+
+        assert_eq!(candle.completeness(&CandleType::Minute), 0.5);
+        assert_eq!(candle.completeness(&CandleType::FifteenMinutes), 7.5);
+    }
+}
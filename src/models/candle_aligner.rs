@@ -0,0 +1,57 @@
+use chrono::{DateTime, Duration, Utc};
+
+use super::candle_type::CandleType;
+
+/// Assigns tick dates to candle periods, tolerating ticks emitted slightly before a candle boundary
+#[derive(Debug, Clone)]
+pub struct CandleAligner {
+    pub candle_type: CandleType,
+    pub tolerance_ms: u64,
+}
+
+impl CandleAligner {
+    pub fn new(candle_type: CandleType, tolerance_ms: u64) -> Self {
+        Self {
+            candle_type,
+            tolerance_ms,
+        }
+    }
+
+    /// Gets start date of the next candle if the date is within tolerance before it,
+    /// otherwise start date of the candle containing the date
+    pub fn align(&self, datetime: DateTime<Utc>) -> DateTime<Utc> {
+        let next_start = self.candle_type.get_end_date(datetime);
+        let tolerance = Duration::milliseconds(self.tolerance_ms as i64);
+
+        if next_start - datetime <= tolerance {
+            return next_start;
+        }
+
+        self.candle_type.get_start_date(datetime)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{candle_aligner::CandleAligner, candle_type::CandleType};
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    #[tokio::test]
+    async fn align_within_tolerance() {
+        let aligner = CandleAligner::new(CandleType::Minute, 100);
+        let boundary: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 1, 0).unwrap();
+
+        assert_eq!(aligner.align(boundary - Duration::milliseconds(50)), boundary);
+        assert_eq!(aligner.align(boundary + Duration::milliseconds(50)), boundary);
+        assert_eq!(aligner.align(boundary - Duration::milliseconds(150)), boundary - Duration::minutes(1));
+    }
+
+    #[tokio::test]
+    async fn align_without_tolerance() {
+        let aligner = CandleAligner::new(CandleType::Minute, 0);
+        let boundary: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 1, 0).unwrap();
+
+        assert_eq!(aligner.align(boundary - Duration::milliseconds(50)), boundary - Duration::minutes(1));
+        assert_eq!(aligner.align(boundary), boundary);
+    }
+}
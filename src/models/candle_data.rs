@@ -1,52 +1,498 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde_derive::{Serialize, Deserialize};
 use serde_with::{serde_as, TimestampSecondsWithFrac};
 
+use super::candle_price::CandlePrice;
 use super::candle_type::CandleType;
+use super::candle_validation_error::CandleValidationError;
+#[cfg(feature = "record-history")]
+use super::ohlcv::Ohlcv;
 
+/// Candle prices and volume. Prices are `f64` unless another `CandlePrice` is specified,
+/// e.g. `rust_decimal::Decimal` with `decimal` feature for exact sums
 #[serde_as]
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CandleData {
-    pub open: f64,
-    pub close: f64,
-    pub high: f64,
-    pub low: f64,
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CandleData<T = f64> {
+    pub open: T,
+    pub close: T,
+    pub high: T,
+    pub low: T,
     #[serde_as(as = "TimestampSecondsWithFrac<f64>")]
     pub datetime: DateTime<Utc>,
-    pub volume: f64,
+    pub volume: T,
+    #[serde(default)]
+    pub tick_count: u64,
+    /// Count of finer candles merged into this candle by resampling
+    #[serde(default)]
+    pub sub_candle_count: u32,
+    /// Accumulated rounding error of `volume` used by compensated summation
+    #[cfg(feature = "precise-volume")]
+    #[serde(default)]
+    pub volume_compensation: T,
+    /// Date, price and volume of every update in update order used by `as_of`. Each update costs
+    /// 32 bytes with `f64` prices, so a minute candle of a 10 ticks per second instrument takes ~19 KB.
+    /// A tick is stored twice (bid and ask) for every configured candle type.
+    /// It's not serialized and is empty for candles not built by `new` and `update`
+    #[cfg(feature = "record-history")]
+    #[serde(skip)]
+    pub history: Vec<(DateTime<Utc>, T, T)>,
 }
 
-impl CandleData {
-    pub fn new(datetime: DateTime<Utc>, price: f64, volume: f64) -> Self {
+impl<T: CandlePrice> CandleData<T> {
+    pub fn new(datetime: DateTime<Utc>, price: T, volume: T) -> Self {
+        Self {
+            open: price,
+            close: price,
+            high: price,
+            low: price,
+            datetime,
+            volume,
+            tick_count: 1,
+            sub_candle_count: 0,
+            #[cfg(feature = "precise-volume")]
+            volume_compensation: T::ZERO,
+            #[cfg(feature = "record-history")]
+            history: vec![(datetime, price, volume)],
+        }
+    }
+
+    /// Creates candle data without any ticks. Prices are set by the first update
+    pub fn new_empty(datetime: DateTime<Utc>) -> Self {
+        Self {
+            open: T::ZERO,
+            close: T::ZERO,
+            high: T::ZERO,
+            low: T::ZERO,
+            datetime,
+            volume: T::ZERO,
+            tick_count: 0,
+            sub_candle_count: 0,
+            #[cfg(feature = "precise-volume")]
+            volume_compensation: T::ZERO,
+            #[cfg(feature = "record-history")]
+            history: Vec::new(),
+        }
+    }
+
+    /// Creates candle data without any ticks and all prices set to specified price
+    pub fn new_flat(datetime: DateTime<Utc>, price: T) -> Self {
         Self {
             open: price,
             close: price,
             high: price,
             low: price,
             datetime,
+            volume: T::ZERO,
+            tick_count: 0,
+            sub_candle_count: 0,
+            #[cfg(feature = "precise-volume")]
+            volume_compensation: T::ZERO,
+            #[cfg(feature = "record-history")]
+            history: Vec::new(),
+        }
+    }
+
+    /// Creates candle from aggregated prices and volume without tick statistics
+    pub fn from_ohlcv(datetime: DateTime<Utc>, open: T, high: T, low: T, close: T, volume: T) -> Self {
+        Self {
+            open,
+            close,
+            high,
+            low,
+            datetime,
             volume,
+            tick_count: 0,
+            sub_candle_count: 0,
+            #[cfg(feature = "precise-volume")]
+            volume_compensation: T::ZERO,
+            #[cfg(feature = "record-history")]
+            history: Vec::new(),
         }
     }
 
-    pub fn update(&mut self, datetime: DateTime<Utc>, price: f64, volume: f64) {
+    pub fn update(&mut self, datetime: DateTime<Utc>, price: T, volume: T) {
         self.close = price;
-        self.volume += volume;
+        self.add_volume(volume);
         self.datetime = datetime;
+        self.tick_count += 1;
 
-        if self.open == 0.0 {
+        #[cfg(feature = "record-history")]
+        self.history.push((datetime, price, volume));
+
+        if self.open == T::ZERO {
             self.open = price;
         }
 
-        if self.high < price || self.high == 0.0 {
+        if self.high < price || self.high == T::ZERO {
+            self.high = price;
+        }
+
+        if self.low > price || self.low == T::ZERO {
+            self.low = price;
+        }
+    }
+
+    /// Gets total volume including the compensated rounding error with `precise-volume` feature
+    pub fn get_volume(&self) -> T {
+        #[cfg(feature = "precise-volume")]
+        return self.volume + self.volume_compensation;
+
+        #[cfg(not(feature = "precise-volume"))]
+        self.volume
+    }
+
+    #[cfg(not(feature = "precise-volume"))]
+    fn add_volume(&mut self, volume: T) {
+        self.volume += volume;
+    }
+
+    /// Neumaier summation keeping low-order bits lost by `volume` in `volume_compensation`
+    #[cfg(feature = "precise-volume")]
+    fn add_volume(&mut self, volume: T) {
+        let sum = self.volume + volume;
+
+        if self.volume.abs() >= volume.abs() {
+            self.volume_compensation += (self.volume - sum) + volume;
+        } else {
+            self.volume_compensation += (volume - sum) + self.volume;
+        }
+
+        self.volume = sum;
+    }
+
+    /// Merges other candle data of the same period into this one.
+    /// Candles must hold ticks of non-overlapping time ranges: only last tick dates are known,
+    /// so the candle with the earlier one is taken as the source of open and the other of close
+    pub fn merge(&mut self, other: &CandleData<T>) {
+        if other.datetime < self.datetime {
+            self.open = other.open;
+        } else {
+            self.close = other.close;
+            self.datetime = other.datetime;
+        }
+
+        if self.high < other.high {
+            self.high = other.high;
+        }
+
+        if self.low > other.low {
+            self.low = other.low;
+        }
+
+        self.add_volume(other.get_volume());
+        self.tick_count += other.tick_count;
+        self.sub_candle_count += other.sub_candle_count;
+
+        #[cfg(feature = "record-history")]
+        {
+            self.history.extend_from_slice(&other.history);
+            self.history.sort_by_key(|(datetime, _price, _volume)| *datetime);
+        }
+    }
+
+    /// Gets share of base candles merged into this candle of specified type.
+    /// It's 0 for candles not built by resampling and above 1 if candles were merged more than once
+    pub fn completeness(&self, candle_type: &CandleType, base: &CandleType) -> f64 {
+        let start = candle_type.get_start_date(self.datetime);
+        let end = candle_type.get_end_date(self.datetime);
+        let expected_count = base.get_dates_count(start, end - Duration::seconds(1));
+
+        if expected_count == 0 {
+            return 0.0;
+        }
+
+        self.sub_candle_count as f64 / expected_count as f64
+    }
+
+    /// Sets open price widening high and low to include it
+    pub fn set_open(&mut self, price: T) {
+        self.open = price;
+
+        if self.high < price {
             self.high = price;
         }
 
-        if self.low > price || self.low == 0.0 {
+        if self.low > price {
             self.low = price;
         }
     }
 
+    pub fn is_same_prices(&self, other: &CandleData<T>) -> bool {
+        self.open == other.open
+            && self.close == other.close
+            && self.high == other.high
+            && self.low == other.low
+    }
+
     pub fn get_candle_date(&self, candle_type: CandleType) -> DateTime<Utc> {
         candle_type.get_start_date(self.datetime)
     }
 }
+
+impl CandleData {
+    /// Updates candle like `update` if price and volume are finite. Otherwise the candle stays unchanged
+    pub fn try_update(&mut self, datetime: DateTime<Utc>, price: f64, volume: f64) -> Result<(), CandleValidationError> {
+        if !price.is_finite() || !volume.is_finite() {
+            return Err(CandleValidationError::NonFiniteValue);
+        }
+
+        self.update(datetime, price, volume);
+
+        Ok(())
+    }
+
+    /// Reconstructs candle using only updates at or before the instant.
+    /// Returns `None` if there are no such updates, including candles built by `from_ohlcv`,
+    /// `new_empty`, `new_flat` or imported from CSV which have no history
+    #[cfg(feature = "record-history")]
+    pub fn as_of(&self, instant: DateTime<Utc>) -> Option<Ohlcv> {
+        let mut updates = self.history.iter().filter(|(datetime, _price, _volume)| *datetime <= instant);
+        let (first_datetime, first_price, first_volume) = updates.next()?;
+        let mut result = Ohlcv {
+            datetime: *first_datetime,
+            open: *first_price,
+            high: *first_price,
+            low: *first_price,
+            close: *first_price,
+            volume: *first_volume,
+        };
+
+        for (datetime, price, volume) in updates {
+            result.datetime = *datetime;
+            result.high = result.high.max(*price);
+            result.low = result.low.min(*price);
+            result.close = *price;
+            result.volume += volume;
+        }
+
+        Some(result)
+    }
+
+    /// Checks price and volume invariants. Returns all found violations
+    pub fn validate(&self) -> Result<(), Vec<CandleValidationError>> {
+        let checks = [
+            (self.high >= self.open, CandleValidationError::HighBelowOpen),
+            (self.high >= self.close, CandleValidationError::HighBelowClose),
+            (self.low <= self.open, CandleValidationError::LowAboveOpen),
+            (self.low <= self.close, CandleValidationError::LowAboveClose),
+            (self.high >= self.low, CandleValidationError::HighBelowLow),
+            (self.get_volume() >= 0.0, CandleValidationError::NegativeVolume),
+            (self.open > 0.0, CandleValidationError::NonPositiveOpen),
+            (
+                [self.open, self.close, self.high, self.low, self.get_volume()].iter().all(|value| value.is_finite()),
+                CandleValidationError::NonFiniteValue,
+            ),
+        ];
+        let errors: Vec<CandleValidationError> = checks
+            .into_iter()
+            .filter(|(is_valid, _)| !is_valid)
+            .map(|(_, error)| error)
+            .collect();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Multiplies open, close, high and low prices by factor
+    pub fn scale_prices(&mut self, factor: f64) {
+        self.open *= factor;
+        self.close *= factor;
+        self.high *= factor;
+        self.low *= factor;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{candle_data::CandleData, candle_type::CandleType, candle_validation_error::CandleValidationError};
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    #[cfg(feature = "record-history")]
+    use crate::models::ohlcv::Ohlcv;
+
+    #[tokio::test]
+    async fn try_update_non_finite() {
+        let datetime: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let mut candle = CandleData::new(datetime, 10.0, 1.0);
+        let initial_candle = candle.clone();
+
+        assert_eq!(candle.try_update(datetime, f64::NAN, 1.0), Err(CandleValidationError::NonFiniteValue));
+        assert_eq!(candle.try_update(datetime, f64::INFINITY, 1.0), Err(CandleValidationError::NonFiniteValue));
+        assert_eq!(candle.try_update(datetime, 11.0, f64::NAN), Err(CandleValidationError::NonFiniteValue));
+        assert_eq!(candle, initial_candle);
+        assert_eq!(candle.try_update(datetime, 11.0, 1.0), Ok(()));
+        assert_eq!(candle.close, 11.0);
+
+        candle.update(datetime, f64::NAN, 1.0);
+
+        assert!(candle.validate().unwrap_err().contains(&CandleValidationError::NonFiniteValue));
+    }
+
+    #[tokio::test]
+    async fn validate() {
+        let datetime: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let mut candle = CandleData::new(datetime, 10.0, 1.0);
+        candle.update(datetime + Duration::seconds(1), 12.0, 1.0);
+        candle.update(datetime + Duration::seconds(2), 9.0, 1.0);
+
+        assert_eq!(candle.validate(), Ok(()));
+
+        let cases = [
+            (CandleValidationError::HighBelowOpen, (13.0, 11.0, 12.0, 9.0, 1.0)),
+            (CandleValidationError::HighBelowClose, (10.0, 13.0, 12.0, 9.0, 1.0)),
+            (CandleValidationError::LowAboveOpen, (8.0, 11.0, 12.0, 9.0, 1.0)),
+            (CandleValidationError::LowAboveClose, (10.0, 8.0, 12.0, 9.0, 1.0)),
+            (CandleValidationError::HighBelowLow, (10.0, 10.0, 9.0, 12.0, 1.0)),
+            (CandleValidationError::NegativeVolume, (10.0, 11.0, 12.0, 9.0, -1.0)),
+            (CandleValidationError::NonPositiveOpen, (0.0, 11.0, 12.0, 0.0, 1.0)),
+        ];
+
+        for (error, (open, close, high, low, volume)) in cases {
+            let mut candle = CandleData::new_empty(datetime);
+            candle.open = open;
+            candle.close = close;
+            candle.high = high;
+            candle.low = low;
+            candle.volume = volume;
+
+            assert!(candle.validate().unwrap_err().contains(&error), "{}", error);
+        }
+    }
+
+    #[tokio::test]
+    async fn tick_count() {
+        let datetime: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let mut candle = CandleData::new(datetime, 10.0, 1.0);
+
+        for i in 1..=10 {
+            candle.update(datetime + Duration::seconds(i), 10.0 + i as f64, 1.0);
+        }
+
+        assert_eq!(candle.tick_count, 11);
+    }
+
+    #[tokio::test]
+    async fn completeness() {
+        let datetime: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 59, 0).unwrap();
+        let mut candle = CandleData::new(datetime, 10.0, 1.0);
+
+        assert_eq!(candle.completeness(&CandleType::Hour, &CandleType::Minute), 0.0);
+
+        candle.sub_candle_count = 30;
+
+        assert_eq!(candle.completeness(&CandleType::Hour, &CandleType::Minute), 0.5);
+        assert_eq!(candle.completeness(&CandleType::Day, &CandleType::Hour), 1.25);
+    }
+
+    #[cfg(feature = "precise-volume")]
+    #[tokio::test]
+    async fn precise_volume() {
+        let datetime: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let mut candle = CandleData::new(datetime, 10.0, 1e8);
+        let mut naive_volume = 1e8;
+
+        for _ in 0..1_000_000 {
+            candle.update(datetime, 10.0, 0.1);
+            naive_volume += 0.1;
+        }
+
+        let expected = 1e8 + 1e5;
+        assert!((candle.get_volume() - expected).abs() < (naive_volume - expected).abs());
+        assert!((candle.get_volume() - expected).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn merge() {
+        let datetime: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let mut candle = CandleData::new(datetime, 10.0, 1.0);
+        candle.update(datetime + Duration::seconds(1), 12.0, 1.0);
+        let mut other = CandleData::new(datetime + Duration::seconds(2), 11.0, 2.0);
+        other.update(datetime + Duration::seconds(3), 9.0, 2.0);
+
+        candle.merge(&other);
+
+        assert_eq!(candle.open, 10.0);
+        assert_eq!(candle.high, 12.0);
+        assert_eq!(candle.low, 9.0);
+        assert_eq!(candle.close, 9.0);
+        assert_eq!(candle.volume, 6.0);
+        assert_eq!(candle.tick_count, 4);
+        assert_eq!(candle.datetime, datetime + Duration::seconds(3));
+    }
+
+    #[tokio::test]
+    async fn merge_sub_candle_count() {
+        let datetime: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let mut candle = CandleData::new(datetime, 10.0, 1.0);
+        candle.sub_candle_count = 15;
+        let mut other = CandleData::new(datetime + Duration::minutes(15), 11.0, 1.0);
+        other.sub_candle_count = 15;
+
+        candle.merge(&other);
+
+        assert_eq!(candle.sub_candle_count, 30);
+    }
+
+    #[cfg(feature = "decimal")]
+    #[tokio::test]
+    async fn decimal_prices() {
+        use rust_decimal::Decimal;
+
+        let datetime: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let mut candle: CandleData<Decimal> = CandleData::new(datetime, Decimal::new(1000, 2), Decimal::new(1, 1));
+        candle.update(datetime + Duration::seconds(1), Decimal::new(1200, 2), Decimal::new(2, 1));
+        candle.update(datetime + Duration::seconds(2), Decimal::new(900, 2), Decimal::new(1, 1));
+        let other = CandleData::new(datetime + Duration::seconds(3), Decimal::new(1100, 2), Decimal::new(3, 1));
+
+        candle.merge(&other);
+
+        assert_eq!(candle.open, Decimal::new(10, 0));
+        assert_eq!(candle.high, Decimal::new(12, 0));
+        assert_eq!(candle.low, Decimal::new(9, 0));
+        assert_eq!(candle.close, Decimal::new(11, 0));
+        assert_eq!(candle.get_volume(), Decimal::new(7, 1));
+        assert_eq!(candle.tick_count, 4);
+    }
+
+    #[cfg(feature = "record-history")]
+    #[tokio::test]
+    async fn as_of() {
+        let datetime: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 14, 0, 0).unwrap();
+        let mut candle = CandleData::new(datetime + Duration::minutes(5), 10.0, 1.0);
+        candle.update(datetime + Duration::minutes(10), 12.0, 2.0);
+        candle.update(datetime + Duration::minutes(25), 9.0, 1.0);
+        candle.update(datetime + Duration::minutes(40), 8.0, 3.0);
+        candle.update(datetime + Duration::minutes(55), 11.0, 1.0);
+
+        let state = candle.as_of(datetime + Duration::minutes(30)).unwrap();
+
+        assert_eq!(state.datetime, datetime + Duration::minutes(25));
+        assert_eq!(state.open, 10.0);
+        assert_eq!(state.high, 12.0);
+        assert_eq!(state.low, 9.0);
+        assert_eq!(state.close, 9.0);
+        assert_eq!(state.volume, 4.0);
+        assert_eq!(candle.as_of(datetime + Duration::minutes(55)).unwrap(), Ohlcv::from(&candle));
+        assert!(candle.as_of(datetime).is_none());
+    }
+
+    #[cfg(feature = "record-history")]
+    #[tokio::test]
+    async fn as_of_out_of_order_updates() {
+        let datetime: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 14, 0, 0).unwrap();
+        let mut candle = CandleData::new(datetime + Duration::minutes(5), 10.0, 1.0);
+        candle.update(datetime + Duration::minutes(40), 8.0, 3.0);
+        candle.update(datetime + Duration::minutes(10), 12.0, 2.0);
+
+        let state = candle.as_of(datetime + Duration::minutes(30)).unwrap();
+
+        assert_eq!(state.datetime, datetime + Duration::minutes(10));
+        assert_eq!(state.high, 12.0);
+        assert_eq!(state.low, 10.0);
+        assert_eq!(state.close, 12.0);
+        assert_eq!(state.volume, 3.0);
+        assert!(CandleData::from_ohlcv(datetime, 10.0, 12.0, 9.0, 11.0, 1.0).as_of(datetime).is_none());
+    }
+}
@@ -261,7 +261,7 @@ mod tests {
 
         let pager = CandlePager {
             instrument: "BTCUSDT".to_string(),
-            candle_type: CandleType::Month,
+            candle_type: CandleType::Monthly,
             from_date: from,
             to_date: to,
             page_id: None,
@@ -295,7 +295,7 @@ mod tests {
         let ids = pager.get_page_candle_ids();
         let mut count = 0;
 
-        while let Some(_) = pager.move_candle_id() {
+        while pager.move_candle_id().is_some() {
             count += 1;
         }
 
@@ -0,0 +1,27 @@
+use std::fmt::Debug;
+use std::ops::{Add, AddAssign, Sub};
+
+/// Numeric type of candle prices and volumes
+pub trait CandlePrice: Copy + Debug + PartialOrd + Add<Output = Self> + Sub<Output = Self> + AddAssign {
+    const ZERO: Self;
+
+    fn abs(self) -> Self;
+}
+
+impl CandlePrice for f64 {
+    const ZERO: Self = 0.0;
+
+    fn abs(self) -> Self {
+        f64::abs(self)
+    }
+}
+
+/// Exact decimal prices for accounting
+#[cfg(feature = "decimal")]
+impl CandlePrice for rust_decimal::Decimal {
+    const ZERO: Self = rust_decimal::Decimal::ZERO;
+
+    fn abs(self) -> Self {
+        rust_decimal::Decimal::abs(&self)
+    }
+}
@@ -0,0 +1,80 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+
+use super::candle_type::CandleType;
+use super::candle_validation_error::CandleValidationError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CandleSdkError {
+    /// Target candle type is not a whole multiple of the source candle type
+    InvalidResampleType {
+        source: CandleType,
+        target: CandleType,
+    },
+    /// Time offset moves candles off their period boundaries
+    UnalignedOffset {
+        candle_type: CandleType,
+        offset_secs: i64,
+    },
+    /// Smoothing factor is out of `(0, 1]` range
+    InvalidSmoothingFactor {
+        alpha: f64,
+    },
+    /// Candle with the same id is already cached
+    DuplicateCandle {
+        id: String,
+    },
+    /// Imported candle violates price or volume invariants
+    InvalidCandle {
+        datetime: DateTime<Utc>,
+        errors: Vec<CandleValidationError>,
+    },
+    /// Rolling window length is not positive
+    InvalidWindow {
+        window_secs: i64,
+    },
+    /// Candle types are requested more than once, so every tick would update their candles repeatedly
+    DuplicateCandleTypes {
+        candle_types: Vec<CandleType>,
+    },
+}
+
+impl fmt::Display for CandleSdkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CandleSdkError::InvalidResampleType { source, target } => write!(
+                f,
+                "Can't resample {:?} candles to {:?}: target type must be evenly divided by source type",
+                source, target
+            ),
+            CandleSdkError::UnalignedOffset {
+                candle_type,
+                offset_secs,
+            } => write!(
+                f,
+                "Offset of {} seconds breaks {:?} candle alignment",
+                offset_secs, candle_type
+            ),
+            CandleSdkError::InvalidSmoothingFactor { alpha } => {
+                write!(f, "Smoothing factor {} must be in (0, 1] range", alpha)
+            }
+            CandleSdkError::DuplicateCandle { id } => write!(f, "Candle {} already exists", id),
+            CandleSdkError::InvalidCandle { datetime, errors } => {
+                let errors: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+
+                write!(f, "Invalid candle at {}: {}", datetime.to_rfc3339(), errors.join(", "))
+            }
+            CandleSdkError::InvalidWindow { window_secs } => {
+                write!(f, "Window of {} seconds must be positive", window_secs)
+            }
+            CandleSdkError::DuplicateCandleTypes { candle_types } => {
+                let candle_types: Vec<String> = candle_types.iter().map(|candle_type| candle_type.to_string()).collect();
+
+                write!(f, "Duplicate candle types: {}", candle_types.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for CandleSdkError {}
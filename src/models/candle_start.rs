@@ -0,0 +1,88 @@
+use crate::models::candle_type::CandleType;
+use chrono::{DateTime, Utc};
+
+/// Start date of a candle period. Can be created only by `CandleType::get_candle_start`,
+/// so the date is always aligned to the candle type
+///
+/// ```compile_fail
+/// use candles_shared::models::{candle_start::CandleStart, candle_type::CandleType};
+/// use chrono::Utc;
+///
+/// let start = CandleStart::new(CandleType::Hour, Utc::now());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CandleStart {
+    candle_type: CandleType,
+    datetime: DateTime<Utc>,
+}
+
+impl CandleStart {
+    pub(crate) fn new(candle_type: CandleType, datetime: DateTime<Utc>) -> Self {
+        Self { candle_type, datetime }
+    }
+
+    pub fn candle_type(&self) -> &CandleType {
+        &self.candle_type
+    }
+
+    pub fn datetime(&self) -> DateTime<Utc> {
+        self.datetime
+    }
+
+    /// Gets exclusive end date of the period
+    pub fn end_date(&self) -> DateTime<Utc> {
+        self.datetime + self.candle_type.get_duration(self.datetime)
+    }
+
+    pub fn next_start(&self) -> CandleStart {
+        self.candle_type.get_candle_start(self.end_date())
+    }
+
+    /// Checks if datetime is within the period
+    pub fn contains(&self, datetime: DateTime<Utc>) -> bool {
+        datetime >= self.datetime && datetime < self.end_date()
+    }
+}
+
+impl From<CandleStart> for DateTime<Utc> {
+    fn from(value: CandleStart) -> Self {
+        value.datetime
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::candle_type::CandleType;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    #[tokio::test]
+    async fn created_only_aligned() {
+        let datetime: DateTime<Utc> = Utc.with_ymd_and_hms(2023, 7, 13, 13, 47, 5).unwrap();
+
+        for candle_type in CandleType::ALL.iter() {
+            let start = candle_type.get_candle_start(datetime);
+
+            assert_eq!(start.candle_type(), candle_type);
+            assert_eq!(start.datetime(), candle_type.get_start_date(datetime));
+            assert_eq!(candle_type.get_candle_start(start.datetime()), start);
+            assert!(start.contains(datetime), "{}", candle_type);
+        }
+    }
+
+    #[tokio::test]
+    async fn next_start() {
+        let datetime: DateTime<Utc> = Utc.with_ymd_and_hms(2023, 1, 31, 13, 47, 5).unwrap();
+        let start = CandleType::Hour.get_candle_start(datetime);
+        let next_start = start.next_start();
+
+        assert_eq!(start.end_date(), Utc.with_ymd_and_hms(2023, 1, 31, 14, 0, 0).unwrap());
+        assert_eq!(next_start.datetime(), start.end_date());
+        assert!(!start.contains(next_start.datetime()));
+        assert!(start.contains(next_start.datetime() - Duration::seconds(1)));
+
+        let month_start = CandleType::Monthly.get_candle_start(datetime);
+
+        assert_eq!(month_start.next_start().datetime(), Utc.with_ymd_and_hms(2023, 2, 1, 0, 0, 0).unwrap());
+        assert_eq!(DateTime::<Utc>::from(month_start), Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap());
+    }
+}
@@ -1,10 +1,14 @@
-use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
 
 use chrono::{DateTime, Datelike, Utc};
 use chrono::{Duration, TimeZone};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
+use super::candle_start::CandleStart;
+use super::exchange::Exchange;
+
 #[derive(
     Serialize_repr,
     Deserialize_repr,
@@ -23,7 +27,8 @@ pub enum CandleType {
     Minute = 0,
     Hour = 1,
     Day = 2,
-    Month = 3,
+    /// Calendar month. Future calendar variants follow the same naming: `Weekly`, `Yearly`
+    Monthly = 3,
     ThreeMinutes = 4,
     FiveMinutes = 5,
     FifteenMinutes = 6,
@@ -35,9 +40,295 @@ pub enum CandleType {
     TwelveHours = 12,
     ThreeDays = 13,
     SevenDays = 14,
+    TwoMinutes = 15,
+    TenMinutes = 16,
+    TwentyMinutes = 17,
+    FortyFiveMinutes = 18,
+    ThirtySeconds = 19,
+    FifteenSeconds = 20,
+    FifteenDays = 21,
+}
+
+impl fmt::Display for CandleType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CandleType::FifteenSeconds => "15s",
+            CandleType::ThirtySeconds => "30s",
+            CandleType::Minute => "1m",
+            CandleType::TwoMinutes => "2m",
+            CandleType::ThreeMinutes => "3m",
+            CandleType::FiveMinutes => "5m",
+            CandleType::TenMinutes => "10m",
+            CandleType::FifteenMinutes => "15m",
+            CandleType::TwentyMinutes => "20m",
+            CandleType::ThirtyMinutes => "30m",
+            CandleType::FortyFiveMinutes => "45m",
+            CandleType::Hour => "1h",
+            CandleType::TwoHours => "2h",
+            CandleType::FourHours => "4h",
+            CandleType::SixHours => "6h",
+            CandleType::EightHours => "8h",
+            CandleType::TwelveHours => "12h",
+            CandleType::Day => "1d",
+            CandleType::ThreeDays => "3d",
+            CandleType::SevenDays => "7d",
+            CandleType::FifteenDays => "15d",
+            CandleType::Monthly => "1M",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for CandleType {
+    type Err = CandleTypeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CandleType::ALL
+            .iter()
+            .find(|candle_type| candle_type.to_string() == s)
+            .cloned()
+            .ok_or_else(|| CandleTypeParseError {
+                value: s.to_string(),
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CandleTypeParseError {
+    pub value: String,
+}
+
+impl fmt::Display for CandleTypeParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown candle type: {}", self.value)
+    }
+}
+
+impl std::error::Error for CandleTypeParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidCandleType {
+    pub value: i32,
+}
+
+impl fmt::Display for InvalidCandleType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let valid_values: Vec<String> = CandleType::ALL
+            .iter()
+            .map(|candle_type| (candle_type.to_owned() as i32).to_string())
+            .collect();
+
+        write!(
+            f,
+            "Invalid candle type value {}; valid values are: {}",
+            self.value,
+            valid_values.join(", ")
+        )
+    }
 }
 
+impl std::error::Error for InvalidCandleType {}
+
 impl CandleType {
+    /// Old name of `CandleType::Monthly`. Deprecated since 0.6.0 and will be removed in 0.7.0
+    #[deprecated(since = "0.6.0", note = "use CandleType::Monthly; Month will be removed in 0.7.0")]
+    #[allow(non_upper_case_globals)]
+    pub const Month: CandleType = CandleType::Monthly;
+
+    pub const ALL: [CandleType; 22] = [
+        CandleType::Minute,
+        CandleType::Hour,
+        CandleType::Day,
+        CandleType::Monthly,
+        CandleType::ThreeMinutes,
+        CandleType::FiveMinutes,
+        CandleType::FifteenMinutes,
+        CandleType::ThirtyMinutes,
+        CandleType::TwoHours,
+        CandleType::FourHours,
+        CandleType::SixHours,
+        CandleType::EightHours,
+        CandleType::TwelveHours,
+        CandleType::ThreeDays,
+        CandleType::SevenDays,
+        CandleType::TwoMinutes,
+        CandleType::TenMinutes,
+        CandleType::TwentyMinutes,
+        CandleType::FortyFiveMinutes,
+        CandleType::ThirtySeconds,
+        CandleType::FifteenSeconds,
+        CandleType::FifteenDays,
+    ];
+
+    pub fn from_i32(v: i32) -> Result<Self, InvalidCandleType> {
+        CandleType::try_from(v).map_err(|_| InvalidCandleType { value: v })
+    }
+
+    pub fn to_iso8601_duration(&self) -> &'static str {
+        match self {
+            CandleType::Minute => "PT1M",
+            CandleType::Hour => "PT1H",
+            CandleType::Day => "P1D",
+            CandleType::Monthly => "P1M",
+            CandleType::ThreeMinutes => "PT3M",
+            CandleType::FiveMinutes => "PT5M",
+            CandleType::FifteenMinutes => "PT15M",
+            CandleType::ThirtyMinutes => "PT30M",
+            CandleType::TwoHours => "PT2H",
+            CandleType::FourHours => "PT4H",
+            CandleType::SixHours => "PT6H",
+            CandleType::EightHours => "PT8H",
+            CandleType::TwelveHours => "PT12H",
+            CandleType::ThreeDays => "P3D",
+            CandleType::SevenDays => "P7D",
+            CandleType::TwoMinutes => "PT2M",
+            CandleType::TenMinutes => "PT10M",
+            CandleType::TwentyMinutes => "PT20M",
+            CandleType::FortyFiveMinutes => "PT45M",
+            CandleType::ThirtySeconds => "PT30S",
+            CandleType::FifteenSeconds => "PT15S",
+            CandleType::FifteenDays => "P15D",
+        }
+    }
+
+    pub fn from_iso8601_duration(s: &str) -> Result<CandleType, CandleTypeParseError> {
+        CandleType::ALL
+            .iter()
+            .find(|candle_type| candle_type.to_iso8601_duration() == s)
+            .cloned()
+            .ok_or_else(|| CandleTypeParseError {
+                value: s.to_string(),
+            })
+    }
+
+    /// Gets interval name used by the venue REST API or `None` if the venue has no such interval.
+    /// `SevenDays` start dates don't match Monday aligned venue weekly intervals
+    pub fn to_exchange_string(&self, venue: Exchange) -> Option<String> {
+        let interval = match venue {
+            Exchange::Binance => match self {
+                CandleType::Minute => "1m",
+                CandleType::ThreeMinutes => "3m",
+                CandleType::FiveMinutes => "5m",
+                CandleType::FifteenMinutes => "15m",
+                CandleType::ThirtyMinutes => "30m",
+                CandleType::Hour => "1h",
+                CandleType::TwoHours => "2h",
+                CandleType::FourHours => "4h",
+                CandleType::SixHours => "6h",
+                CandleType::EightHours => "8h",
+                CandleType::TwelveHours => "12h",
+                CandleType::Day => "1d",
+                CandleType::ThreeDays => "3d",
+                CandleType::Monthly => "1M",
+                CandleType::SevenDays
+                | CandleType::TwoMinutes
+                | CandleType::TenMinutes
+                | CandleType::TwentyMinutes
+                | CandleType::FortyFiveMinutes
+                | CandleType::ThirtySeconds
+                | CandleType::FifteenSeconds
+                | CandleType::FifteenDays => return None,
+            },
+            Exchange::Bybit => match self {
+                CandleType::Minute => "1",
+                CandleType::ThreeMinutes => "3",
+                CandleType::FiveMinutes => "5",
+                CandleType::FifteenMinutes => "15",
+                CandleType::ThirtyMinutes => "30",
+                CandleType::Hour => "60",
+                CandleType::TwoHours => "120",
+                CandleType::FourHours => "240",
+                CandleType::SixHours => "360",
+                CandleType::TwelveHours => "720",
+                CandleType::Day => "D",
+                CandleType::Monthly => "M",
+                CandleType::SevenDays
+                | CandleType::EightHours
+                | CandleType::ThreeDays
+                | CandleType::TwoMinutes
+                | CandleType::TenMinutes
+                | CandleType::TwentyMinutes
+                | CandleType::FortyFiveMinutes
+                | CandleType::ThirtySeconds
+                | CandleType::FifteenSeconds
+                | CandleType::FifteenDays => return None,
+            },
+            // okx aligns 6h and longer candles to Hong Kong time unless the utc suffix is used
+            Exchange::Okx => match self {
+                CandleType::Minute => "1m",
+                CandleType::ThreeMinutes => "3m",
+                CandleType::FiveMinutes => "5m",
+                CandleType::FifteenMinutes => "15m",
+                CandleType::ThirtyMinutes => "30m",
+                CandleType::Hour => "1H",
+                CandleType::TwoHours => "2H",
+                CandleType::FourHours => "4H",
+                CandleType::SixHours => "6Hutc",
+                CandleType::TwelveHours => "12Hutc",
+                CandleType::Day => "1Dutc",
+                CandleType::ThreeDays => "3Dutc",
+                CandleType::Monthly => "1Mutc",
+                CandleType::SevenDays
+                | CandleType::EightHours
+                | CandleType::TwoMinutes
+                | CandleType::TenMinutes
+                | CandleType::TwentyMinutes
+                | CandleType::FortyFiveMinutes
+                | CandleType::ThirtySeconds
+                | CandleType::FifteenSeconds
+                | CandleType::FifteenDays => return None,
+            },
+        };
+
+        Some(interval.to_string())
+    }
+
+    /// Gets candle type by Kraken REST API interval in minutes
+    pub fn parse_kraken_interval(minutes: u32) -> Option<CandleType> {
+        CandleType::ALL
+            .iter()
+            .find(|candle_type| candle_type.to_kraken_interval() == Some(minutes))
+            .cloned()
+    }
+
+    /// Gets Kraken REST API interval in minutes or `None` if Kraken has no such interval
+    pub fn to_kraken_interval(&self) -> Option<u32> {
+        match self {
+            CandleType::Minute => Some(1),
+            CandleType::FiveMinutes => Some(5),
+            CandleType::FifteenMinutes => Some(15),
+            CandleType::ThirtyMinutes => Some(30),
+            CandleType::Hour => Some(60),
+            CandleType::FourHours => Some(240),
+            CandleType::Day => Some(1440),
+            CandleType::SevenDays => Some(10080),
+            CandleType::FifteenDays => Some(21600),
+            _ => None,
+        }
+    }
+
+    /// Gets candle type by Coinbase REST API granularity in seconds
+    pub fn parse_coinbase_granularity(secs: u32) -> Option<CandleType> {
+        CandleType::ALL
+            .iter()
+            .find(|candle_type| candle_type.to_coinbase_granularity() == Some(secs))
+            .cloned()
+    }
+
+    /// Gets Coinbase REST API granularity in seconds or `None` if Coinbase has no such granularity
+    pub fn to_coinbase_granularity(&self) -> Option<u32> {
+        match self {
+            CandleType::Minute => Some(60),
+            CandleType::FiveMinutes => Some(300),
+            CandleType::FifteenMinutes => Some(900),
+            CandleType::Hour => Some(3600),
+            CandleType::SixHours => Some(21600),
+            CandleType::Day => Some(86400),
+            _ => None,
+        }
+    }
+
     pub fn get_start_date(&self, datetime: DateTime<Utc>) -> DateTime<Utc> {
         let timestamp_sec = datetime.timestamp();
 
@@ -51,7 +342,7 @@ impl CandleType {
             CandleType::Day => Utc
                 .timestamp_millis_opt((timestamp_sec - timestamp_sec % 86400) * 1000)
                 .unwrap(),
-            CandleType::Month => {
+            CandleType::Monthly => {
                 let date = Utc.timestamp_millis_opt(timestamp_sec * 1000).unwrap();
                 let start_of_month: DateTime<Utc> = Utc
                     .with_ymd_and_hms(date.year(), date.month(), 1, 0, 0, 0)
@@ -92,30 +383,72 @@ impl CandleType {
             CandleType::SevenDays => Utc
                 .timestamp_millis_opt((timestamp_sec - timestamp_sec % 1036800) * 1000)
                 .unwrap(),
+            CandleType::TwoMinutes => Utc
+                .timestamp_millis_opt((timestamp_sec - timestamp_sec % 120) * 1000)
+                .unwrap(),
+            CandleType::TenMinutes => Utc
+                .timestamp_millis_opt((timestamp_sec - timestamp_sec % 600) * 1000)
+                .unwrap(),
+            CandleType::TwentyMinutes => Utc
+                .timestamp_millis_opt((timestamp_sec - timestamp_sec % 1200) * 1000)
+                .unwrap(),
+            CandleType::FortyFiveMinutes => Utc
+                .timestamp_millis_opt((timestamp_sec - timestamp_sec % 2700) * 1000)
+                .unwrap(),
+            CandleType::ThirtySeconds => Utc
+                .timestamp_millis_opt((timestamp_sec - timestamp_sec % 30) * 1000)
+                .unwrap(),
+            CandleType::FifteenSeconds => Utc
+                .timestamp_millis_opt((timestamp_sec - timestamp_sec % 15) * 1000)
+                .unwrap(),
+            CandleType::FifteenDays => Utc
+                .timestamp_millis_opt((timestamp_sec - timestamp_sec % 1296000) * 1000)
+                .unwrap(),
         }
     }
 
+    /// Gets start of the candle period containing specified date
+    pub fn get_candle_start(&self, datetime: DateTime<Utc>) -> CandleStart {
+        CandleStart::new(self.clone(), self.get_start_date(datetime))
+    }
+
+    /// Gets ascending and duplicate-free start dates of all candles in the specified range
     pub fn get_start_dates(
         &self,
         datetime_from: DateTime<Utc>,
         datetime_to: DateTime<Utc>,
-    ) -> HashSet<DateTime<Utc>> {
-        let mut dates = HashSet::new();
+    ) -> Vec<DateTime<Utc>> {
         let date_from = self.get_start_date(datetime_from);
-        dates.insert(date_from);
         let date_to = self.get_start_date(datetime_to);
-
-        let mut last_date = self.get_start_date(date_from);
+        // dates are produced in order, so no deduplication is needed
+        let mut dates = Vec::with_capacity(self.get_dates_count(date_from, date_to));
+        dates.push(date_from);
+        let mut last_date = date_from;
 
         while last_date < date_to {
-            let next_date = self.get_start_date(last_date) + self.get_duration(last_date);
-            last_date = self.get_start_date(next_date);
-            dates.insert(last_date);
+            last_date = self.get_start_date(last_date + self.get_duration(last_date));
+            dates.push(last_date);
         }
 
         dates
     }
 
+    /// Gets start and exclusive end of the candle period containing specified date
+    pub fn get_boundary_dates(&self, datetime: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+        let start = self.get_start_date(datetime);
+
+        (start, start + self.get_duration(start))
+    }
+
+    /// Gets start date of the period preceding the period containing specified date
+    pub fn get_prev_start_date(&self, datetime: DateTime<Utc>) -> DateTime<Utc> {
+        self.get_start_date(self.get_start_date(datetime) - Duration::seconds(1))
+    }
+
+    /// Gets the date when the candle of the period containing specified date closes
+    pub fn next_close(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        self.get_end_date(self.get_start_date(now))
+    }
 
     pub fn get_end_date(
         &self,
@@ -127,41 +460,137 @@ impl CandleType {
         start + duration
     }
 
+    /// Gets exclusive end date of the candle period starting at `start` without aligning it again.
+    /// Start of another candle type is aligned to this type
+    pub fn get_end_date_of(&self, start: &CandleStart) -> DateTime<Utc> {
+        if start.candle_type() == self {
+            return start.end_date();
+        }
+
+        self.get_end_date(start.datetime())
+    }
+
+    /// Gets count of candles in the specified range including both bounds.
+    /// Gives 0 if `datetime_to` precedes `datetime_from` and saturates at `usize::MAX`
     pub fn get_dates_count(&self, datetime_from: DateTime<Utc>, datetime_to: DateTime<Utc>) -> usize {
         let from = self.get_start_date(datetime_from);
         let to = self.get_end_date(datetime_to);
 
-        match self {
-            CandleType::Month =>  {
+        let count: i64 = match self {
+            CandleType::Monthly =>  {
+                // months are signed, so ranges crossing a year end don't underflow
                 let year_diff = to.year() - from.year();
-                let month_diff = to.month() - from.month();
-                let total_month_diff = year_diff * 12 + month_diff as i32;
+                let month_diff = to.month() as i32 - from.month() as i32;
+                let total_month_diff = year_diff * 12 + month_diff;
 
-                total_month_diff as usize
+                total_month_diff as i64
             },
             CandleType::Minute => {
                 let duration = to.signed_duration_since(from);
-                let minute_count = duration.num_minutes();
-
-                minute_count as usize
+                duration.num_minutes()
             },
             _ => {
-                let duration = self.get_duration(datetime_from);
-                let duration_between = to - from;
-                let count = duration_between.num_seconds() / duration.num_seconds();
-
-                count as usize
+                // count period starts between aligned bounds, so sub-period offsets
+                // of the input dates never add an extra period
+                let duration = self.get_duration(from);
+                let last_start = self.get_start_date(datetime_to);
+                (last_start - from).num_seconds() / duration.num_seconds() + 1
             }
+        };
+
+        usize::try_from(count.max(0)).unwrap_or(usize::MAX)
+    }
+
+    /// Gets fixed candle types which evenly subdivide this type ordered by duration.
+    /// Calendar types are never included; a month is subdivided by the divisors of a day and the day itself
+    pub fn divisors(&self) -> Vec<CandleType> {
+        let reference_date = DateTime::<Utc>::UNIX_EPOCH;
+        let (duration, include_self) = if self.is_calendar() {
+            (CandleType::Day.get_duration(reference_date), true)
+        } else {
+            (self.get_duration(reference_date), false)
+        };
+
+        let mut divisors: Vec<CandleType> = CandleType::ALL
+            .iter()
+            .filter(|candle_type| !candle_type.is_calendar())
+            .filter(|candle_type| {
+                let divisor = candle_type.get_duration(reference_date);
+
+                duration.num_seconds() % divisor.num_seconds() == 0
+                    && (divisor < duration || (include_self && divisor == duration))
+            })
+            .cloned()
+            .collect();
+        divisors.sort_by_key(|candle_type| candle_type.get_duration(reference_date));
+
+        divisors
+    }
+
+    fn is_calendar(&self) -> bool {
+        matches!(self, CandleType::Monthly)
+    }
+
+    /// Checks if start dates are computed by modulo from the Unix epoch instead of calendar boundaries
+    pub fn is_epoch_aligned(&self) -> bool {
+        !self.is_calendar()
+    }
+
+    /// Checks if candles of this type are shorter than an hour
+    pub fn is_sub_hour(&self) -> bool {
+        self.try_fixed_duration()
+            .is_some_and(|duration| duration < Duration::hours(1))
+    }
+
+    /// Checks if candles of this type are longer than candles of other type
+    pub fn is_coarser_than(&self, other: &CandleType) -> bool {
+        let reference_date = DateTime::<Utc>::UNIX_EPOCH;
 
+        self.get_duration(reference_date) > other.get_duration(reference_date)
+    }
+
+    /// Checks if candles of both types have the same duration at the reference date
+    pub fn same_duration_as(&self, other: &CandleType, reference_datetime: DateTime<Utc>) -> bool {
+        self.get_duration(reference_datetime) == other.get_duration(reference_datetime)
+    }
+
+    /// Gets default retention window: 1000 candles for fixed types and 5 years for months
+    pub fn suggested_retention(&self) -> Duration {
+        match self.try_fixed_duration() {
+            Some(duration) => duration * 1000,
+            None => Duration::days(365 * 5 + 1),
         }
     }
 
+    /// Gets seconds count of fixed-length candle types and `None` for calendar types
+    pub fn as_secs(&self) -> Option<u64> {
+        self.try_fixed_duration().map(|duration| duration.num_seconds() as u64)
+    }
+
+    /// Gets fixed-length candle type by its seconds count
+    pub fn from_seconds(secs: u64) -> Option<CandleType> {
+        CandleType::ALL
+            .iter()
+            .find(|candle_type| candle_type.as_secs() == Some(secs))
+            .cloned()
+    }
+
+    /// Gets duration of fixed-length candle types and `None` for calendar types with variable length
+    pub fn try_fixed_duration(&self) -> Option<Duration> {
+        if self.is_calendar() {
+            return None;
+        }
+
+        // fixed durations don't depend on the datetime
+        Some(self.get_duration(DateTime::<Utc>::UNIX_EPOCH))
+    }
+
     pub fn get_duration(&self, datetime: DateTime<Utc>) -> Duration {
-        let duration = match self {
+        match self {
             CandleType::Minute => Duration::seconds(60),
             CandleType::Hour => Duration::seconds(3600),
             CandleType::Day => Duration::seconds(86400),
-            CandleType::Month => {
+            CandleType::Monthly => {
                 let start_of_month: DateTime<Utc> = Utc
                     .with_ymd_and_hms(datetime.year(), datetime.month(), 1, 0, 0, 0)
                     .unwrap();
@@ -194,19 +623,332 @@ impl CandleType {
             CandleType::TwelveHours => Duration::hours(12),
             CandleType::ThreeDays => Duration::days(3),
             CandleType::SevenDays => Duration::days(7),
-        };
-
-        duration
+            CandleType::TwoMinutes => Duration::minutes(2),
+            CandleType::TenMinutes => Duration::minutes(10),
+            CandleType::TwentyMinutes => Duration::minutes(20),
+            CandleType::FortyFiveMinutes => Duration::minutes(45),
+            CandleType::ThirtySeconds => Duration::seconds(30),
+            CandleType::FifteenSeconds => Duration::seconds(15),
+            CandleType::FifteenDays => Duration::days(15),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use std::collections::HashSet;
+    use std::str::FromStr;
 
-    use crate::models::candle_type::CandleType;
+    use crate::models::{candle_type::CandleType, exchange::Exchange};
     use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike, Utc};
 
+    #[tokio::test]
+    async fn iso8601_duration_round_trip() {
+        for candle_type in CandleType::ALL.iter() {
+            let duration = candle_type.to_iso8601_duration();
+            let parsed = CandleType::from_iso8601_duration(duration);
+
+            assert_eq!(parsed, Ok(candle_type.clone()));
+        }
+    }
+
+    #[tokio::test]
+    async fn iso8601_duration_invalid() {
+        assert!(CandleType::from_iso8601_duration("PT1H30M").is_err());
+        assert!(CandleType::from_iso8601_duration("").is_err());
+        assert!(CandleType::from_iso8601_duration("pt1h").is_err());
+    }
+
+    #[tokio::test]
+    async fn from_i32() {
+        assert_eq!(CandleType::from_i32(9), Ok(CandleType::FourHours));
+
+        let error = CandleType::from_i32(99).unwrap_err();
+        assert_eq!(error.value, 99);
+        assert_eq!(
+            error.to_string(),
+            "Invalid candle type value 99; valid values are: 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21"
+        );
+    }
+
+    #[tokio::test]
+    async fn divisors() {
+        assert_eq!(
+            CandleType::Hour.divisors(),
+            vec![
+                CandleType::FifteenSeconds,
+                CandleType::ThirtySeconds,
+                CandleType::Minute,
+                CandleType::TwoMinutes,
+                CandleType::ThreeMinutes,
+                CandleType::FiveMinutes,
+                CandleType::TenMinutes,
+                CandleType::FifteenMinutes,
+                CandleType::TwentyMinutes,
+                CandleType::ThirtyMinutes,
+            ]
+        );
+        assert_eq!(CandleType::Minute.divisors(), vec![CandleType::FifteenSeconds, CandleType::ThirtySeconds]);
+        assert!(CandleType::FifteenSeconds.divisors().is_empty());
+        assert!(!CandleType::SevenDays.divisors().contains(&CandleType::ThreeDays));
+        assert!(CandleType::Monthly.divisors().contains(&CandleType::Day));
+        assert!(!CandleType::Monthly.divisors().contains(&CandleType::Monthly));
+    }
+
+    #[tokio::test]
+    async fn to_exchange_string_binance() {
+        assert_eq!(CandleType::Minute.to_exchange_string(Exchange::Binance), Some("1m".to_string()));
+        assert_eq!(CandleType::Hour.to_exchange_string(Exchange::Binance), Some("1h".to_string()));
+        assert_eq!(CandleType::EightHours.to_exchange_string(Exchange::Binance), Some("8h".to_string()));
+        assert_eq!(CandleType::Monthly.to_exchange_string(Exchange::Binance), Some("1M".to_string()));
+        assert_eq!(CandleType::TenMinutes.to_exchange_string(Exchange::Binance), None);
+    }
+
+    #[tokio::test]
+    async fn to_exchange_string_unsupported() {
+        assert_eq!(CandleType::EightHours.to_exchange_string(Exchange::Bybit), None);
+        assert_eq!(CandleType::EightHours.to_exchange_string(Exchange::Okx), None);
+        assert_eq!(CandleType::Hour.to_exchange_string(Exchange::Bybit), Some("60".to_string()));
+        assert_eq!(CandleType::Day.to_exchange_string(Exchange::Okx), Some("1Dutc".to_string()));
+
+        for venue in [Exchange::Binance, Exchange::Bybit, Exchange::Okx] {
+            assert_eq!(CandleType::SevenDays.to_exchange_string(venue), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn kraken_interval_round_trip() {
+        let intervals = [
+            (1, CandleType::Minute),
+            (5, CandleType::FiveMinutes),
+            (15, CandleType::FifteenMinutes),
+            (30, CandleType::ThirtyMinutes),
+            (60, CandleType::Hour),
+            (240, CandleType::FourHours),
+            (1440, CandleType::Day),
+            (10080, CandleType::SevenDays),
+            (21600, CandleType::FifteenDays),
+        ];
+
+        for (minutes, candle_type) in intervals {
+            assert_eq!(CandleType::parse_kraken_interval(minutes), Some(candle_type.clone()));
+            assert_eq!(candle_type.to_kraken_interval(), Some(minutes));
+            assert_eq!(candle_type.as_secs(), Some(minutes as u64 * 60));
+        }
+
+        assert_eq!(CandleType::TwoHours.to_kraken_interval(), None);
+        assert_eq!(CandleType::Monthly.to_kraken_interval(), None);
+        assert_eq!(CandleType::parse_kraken_interval(120), None);
+    }
+
+    #[tokio::test]
+    async fn coinbase_granularity_round_trip() {
+        let granularities = [
+            (60, CandleType::Minute),
+            (300, CandleType::FiveMinutes),
+            (900, CandleType::FifteenMinutes),
+            (3600, CandleType::Hour),
+            (21600, CandleType::SixHours),
+            (86400, CandleType::Day),
+        ];
+
+        for (secs, candle_type) in granularities {
+            assert_eq!(CandleType::parse_coinbase_granularity(secs), Some(candle_type.clone()));
+            assert_eq!(candle_type.to_coinbase_granularity(), Some(secs));
+        }
+
+        assert_eq!(CandleType::ThreeMinutes.to_coinbase_granularity(), None);
+        assert_eq!(CandleType::Monthly.to_coinbase_granularity(), None);
+        assert_eq!(CandleType::parse_coinbase_granularity(180), None);
+    }
+
+    #[tokio::test]
+    async fn display_from_str_round_trip() {
+        for candle_type in CandleType::ALL.iter() {
+            let name = candle_type.to_string();
+
+            assert_eq!(CandleType::from_str(&name), Ok(candle_type.clone()));
+        }
+
+        assert_eq!(CandleType::FortyFiveMinutes.to_string(), "45m");
+        assert!(CandleType::from_str("45M").is_err());
+    }
+
+    #[tokio::test]
+    async fn get_start_date_for_new_sub_hour_types() {
+        let src_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 1, 59, 34).unwrap();
+
+        assert_eq!(CandleType::TwoMinutes.get_start_date(src_date), Utc.with_ymd_and_hms(2000, 1, 1, 1, 58, 0).unwrap());
+        assert_eq!(CandleType::TenMinutes.get_start_date(src_date), Utc.with_ymd_and_hms(2000, 1, 1, 1, 50, 0).unwrap());
+        assert_eq!(CandleType::TwentyMinutes.get_start_date(src_date), Utc.with_ymd_and_hms(2000, 1, 1, 1, 40, 0).unwrap());
+        assert_eq!(CandleType::FortyFiveMinutes.get_start_date(src_date), Utc.with_ymd_and_hms(2000, 1, 1, 1, 30, 0).unwrap());
+        assert_eq!(CandleType::FortyFiveMinutes.get_duration(src_date), Duration::minutes(45));
+    }
+
+    #[tokio::test]
+    async fn sub_minute_types() {
+        let src_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 1, 7, 44).unwrap();
+
+        assert_eq!(CandleType::ThirtySeconds.get_start_date(src_date), Utc.with_ymd_and_hms(2000, 1, 1, 1, 7, 30).unwrap());
+        assert_eq!(CandleType::FifteenSeconds.get_start_date(src_date), Utc.with_ymd_and_hms(2000, 1, 1, 1, 7, 30).unwrap());
+        assert_eq!(
+            CandleType::FifteenSeconds.get_start_date(src_date + Duration::seconds(1)),
+            Utc.with_ymd_and_hms(2000, 1, 1, 1, 7, 45).unwrap()
+        );
+        assert_eq!(CandleType::ThirtySeconds.as_secs(), Some(30));
+        assert_eq!(CandleType::FifteenSeconds.as_secs(), Some(15));
+        assert_eq!(CandleType::from_str("30s"), Ok(CandleType::ThirtySeconds));
+        assert_eq!(CandleType::from_str("15s"), Ok(CandleType::FifteenSeconds));
+        assert_eq!(CandleType::FifteenSeconds.get_start_dates(src_date, src_date + Duration::minutes(1)).len(), 5);
+        assert!(CandleType::ThirtySeconds.is_sub_hour());
+        assert!(CandleType::FifteenSeconds.is_sub_hour());
+        assert!(CandleType::FortyFiveMinutes.is_sub_hour());
+        assert!(!CandleType::Hour.is_sub_hour());
+        assert!(!CandleType::Monthly.is_sub_hour());
+    }
+
+    #[tokio::test]
+    #[allow(deprecated)]
+    async fn deprecated_month_alias() {
+        assert_eq!(CandleType::Month, CandleType::Monthly);
+        assert!(matches!(CandleType::Monthly, CandleType::Month));
+        assert_eq!(CandleType::Month as i32, 3);
+    }
+
+    #[tokio::test]
+    async fn try_fixed_duration() {
+        let reference_date: DateTime<Utc> = Utc.with_ymd_and_hms(2023, 7, 19, 13, 47, 5).unwrap();
+
+        for candle_type in CandleType::ALL.iter() {
+            let duration = candle_type.try_fixed_duration();
+
+            if *candle_type == CandleType::Monthly {
+                assert_eq!(duration, None);
+            } else {
+                assert_eq!(duration, Some(candle_type.get_duration(reference_date)));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn same_duration_as() {
+        let reference_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 2, 1, 0, 0, 0).unwrap();
+
+        for candle_type in CandleType::ALL.iter() {
+            assert!(candle_type.same_duration_as(&candle_type.clone(), reference_date));
+
+            for other in CandleType::ALL.iter().filter(|other| *other != candle_type) {
+                assert!(!candle_type.same_duration_as(other, reference_date), "{} {}", candle_type, other);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn is_epoch_aligned() {
+        for candle_type in CandleType::ALL.iter() {
+            let expected = match candle_type {
+                CandleType::Monthly => false,
+                CandleType::FifteenSeconds
+                | CandleType::ThirtySeconds
+                | CandleType::Minute
+                | CandleType::TwoMinutes
+                | CandleType::ThreeMinutes
+                | CandleType::FiveMinutes
+                | CandleType::TenMinutes
+                | CandleType::FifteenMinutes
+                | CandleType::TwentyMinutes
+                | CandleType::ThirtyMinutes
+                | CandleType::FortyFiveMinutes
+                | CandleType::Hour
+                | CandleType::TwoHours
+                | CandleType::FourHours
+                | CandleType::SixHours
+                | CandleType::EightHours
+                | CandleType::TwelveHours
+                | CandleType::Day
+                | CandleType::ThreeDays
+                | CandleType::SevenDays
+                | CandleType::FifteenDays => true,
+            };
+
+            assert_eq!(candle_type.is_epoch_aligned(), expected, "{}", candle_type);
+        }
+    }
+
+    #[tokio::test]
+    async fn from_seconds_round_trip() {
+        for candle_type in CandleType::ALL.iter() {
+            match candle_type.as_secs() {
+                Some(secs) => assert_eq!(CandleType::from_seconds(secs).as_ref(), Some(candle_type)),
+                None => assert_eq!(*candle_type, CandleType::Monthly),
+            }
+        }
+
+        assert_eq!(CandleType::Hour.as_secs(), Some(3600));
+        assert_eq!(CandleType::from_seconds(0), None);
+        assert_eq!(CandleType::from_seconds(61), None);
+        assert_eq!(CandleType::from_seconds(30 * 24 * 3600), None);
+    }
+
+    #[tokio::test]
+    async fn get_boundary_dates() {
+        let datetime: DateTime<Utc> = Utc.with_ymd_and_hms(2024, 2, 29, 13, 47, 5).unwrap();
+
+        for candle_type in CandleType::ALL.iter() {
+            let (start, end) = candle_type.get_boundary_dates(datetime);
+
+            assert!(start < end, "{}", candle_type);
+            assert_eq!(start, candle_type.get_start_date(datetime));
+            assert_eq!(end, candle_type.get_end_date(datetime));
+            assert_eq!(end - start, candle_type.get_duration(start));
+        }
+
+        assert_eq!(
+            CandleType::Monthly.get_boundary_dates(datetime),
+            (Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap(), Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn get_prev_start_date() {
+        let datetime: DateTime<Utc> = Utc.with_ymd_and_hms(2024, 3, 10, 13, 47, 5).unwrap();
+
+        assert_eq!(CandleType::Minute.get_prev_start_date(datetime), Utc.with_ymd_and_hms(2024, 3, 10, 13, 46, 0).unwrap());
+        assert_eq!(CandleType::Hour.get_prev_start_date(datetime), Utc.with_ymd_and_hms(2024, 3, 10, 12, 0, 0).unwrap());
+        assert_eq!(CandleType::Monthly.get_prev_start_date(datetime), Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap());
+        assert_eq!(
+            CandleType::Monthly.get_prev_start_date(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()),
+            Utc.with_ymd_and_hms(2023, 12, 1, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn next_close() {
+        let now: DateTime<Utc> = Utc.with_ymd_and_hms(2024, 2, 10, 13, 47, 5).unwrap();
+
+        assert_eq!(CandleType::Hour.next_close(now), Utc.with_ymd_and_hms(2024, 2, 10, 14, 0, 0).unwrap());
+        assert_eq!(CandleType::Monthly.next_close(now), Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap());
+        assert_eq!(
+            CandleType::Monthly.next_close(Utc.with_ymd_and_hms(2024, 12, 31, 23, 59, 59).unwrap()),
+            Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn suggested_retention() {
+        let mut candle_types: Vec<CandleType> = CandleType::ALL
+            .iter()
+            .filter(|candle_type| candle_type.try_fixed_duration().is_some())
+            .cloned()
+            .collect();
+        candle_types.sort_by_key(|candle_type| candle_type.try_fixed_duration());
+
+        assert!(candle_types.windows(2).all(|pair| pair[0].suggested_retention() < pair[1].suggested_retention()));
+        assert_eq!(CandleType::Minute.suggested_retention(), Duration::minutes(1000));
+        assert_eq!(CandleType::Day.suggested_retention(), Duration::days(1000));
+        assert!(CandleType::Monthly.suggested_retention() > CandleType::Day.suggested_retention());
+    }
+
     #[tokio::test]
     async fn count_minute() {
         let candle_type = CandleType::Minute;
@@ -219,6 +961,21 @@ mod tests {
         assert_eq!(count, duration.num_minutes() as usize);
     }
 
+    #[tokio::test]
+    async fn count_large_and_reversed_ranges() {
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(1970, 1, 1, 0, 0, 0).unwrap();
+        let to: DateTime<Utc> = Utc.with_ymd_and_hms(2070, 1, 1, 0, 0, 0).unwrap();
+        let days = 100 * 365 + 25;
+
+        assert_eq!(CandleType::Minute.get_dates_count(from, to), days * 24 * 60 + 1);
+        assert_eq!(CandleType::FifteenSeconds.get_dates_count(from, to), days * 24 * 60 * 4 + 1);
+        assert_eq!(CandleType::Monthly.get_dates_count(from, to), 100 * 12 + 1);
+
+        for candle_type in CandleType::ALL.iter() {
+            assert_eq!(candle_type.get_dates_count(to, from), 0, "{}", candle_type);
+        }
+    }
+
     #[tokio::test]
     async fn count_hour() {
         let candle_type = CandleType::Hour;
@@ -231,6 +988,44 @@ mod tests {
         assert_eq!(count, duration.num_hours() as usize);
     }
 
+    #[tokio::test]
+    async fn count_sub_hour() {
+        let candle_types = [
+            CandleType::TwoMinutes,
+            CandleType::ThreeMinutes,
+            CandleType::FiveMinutes,
+            CandleType::TenMinutes,
+            CandleType::FifteenMinutes,
+            CandleType::TwentyMinutes,
+            CandleType::ThirtyMinutes,
+        ];
+        let range = Duration::hours(2);
+        let base: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for candle_type in candle_types {
+            let duration = candle_type.get_duration(base);
+            let offsets = [Duration::zero(), Duration::seconds(1), duration / 2, duration - Duration::seconds(1)];
+
+            for offset in offsets {
+                let from = base + offset;
+                let to = base + range - Duration::seconds(1) - offset;
+
+                let count = candle_type.get_dates_count(from, to);
+
+                assert_eq!(
+                    count,
+                    (range.num_seconds() / duration.num_seconds()) as usize,
+                    "{:?} with offset {}",
+                    candle_type,
+                    offset
+                );
+            }
+        }
+
+        let count = CandleType::ThirtyMinutes.get_dates_count(base + Duration::minutes(10), base + Duration::minutes(110));
+        assert_eq!(count, 4);
+    }
+
     #[tokio::test]
     async fn count_day() {
         let candle_type = CandleType::Day;
@@ -245,9 +1040,8 @@ mod tests {
 
     
     #[tokio::test]
-    #[ignore]
     async fn count_month() {
-        let candle_type = CandleType::Month;
+        let candle_type = CandleType::Monthly;
         let num_months = 12;
         let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
         let to: DateTime<Utc> = Utc.with_ymd_and_hms(2000, num_months, 1, 0, 0, 0).unwrap();
@@ -255,6 +1049,12 @@ mod tests {
         let count = candle_type.get_dates_count(from, to);
 
         assert_eq!(count, num_months as usize);
+        assert_eq!(count, candle_type.get_start_dates(from, to).len());
+
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 11, 15, 0, 0, 0).unwrap();
+        let to: DateTime<Utc> = Utc.with_ymd_and_hms(2001, 2, 1, 0, 0, 0).unwrap();
+
+        assert_eq!(candle_type.get_dates_count(from, to), 4);
     }
 
     #[tokio::test]
@@ -305,7 +1105,7 @@ mod tests {
 
     #[tokio::test]
     async fn get_start_date_for_month() {
-        let candle_type = CandleType::Month;
+        let candle_type = CandleType::Monthly;
         let src_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 12, 3, 23, 34).unwrap();
 
         let start_date = candle_type.get_start_date(src_date);
@@ -320,7 +1120,7 @@ mod tests {
 
     #[tokio::test]
     async fn get_end_date_for_month() {
-        let candle_type = CandleType::Month;
+        let candle_type = CandleType::Monthly;
         let src_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 12, 12, 3, 23, 34).unwrap();
 
         let end_date = candle_type.get_end_date(src_date);
@@ -333,6 +1133,63 @@ mod tests {
         assert_eq!(end_date.second(), 0);
     }
 
+    #[tokio::test]
+    async fn get_end_date_of() {
+        let src_date: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 2, 12, 3, 23, 34).unwrap();
+        let month_start = CandleType::Monthly.get_candle_start(src_date);
+        let hour_start = CandleType::Hour.get_candle_start(src_date);
+
+        assert_eq!(CandleType::Monthly.get_end_date_of(&month_start), Utc.with_ymd_and_hms(2000, 3, 1, 0, 0, 0).unwrap());
+        assert_eq!(CandleType::Hour.get_end_date_of(&hour_start), Utc.with_ymd_and_hms(2000, 2, 12, 4, 0, 0).unwrap());
+        assert_eq!(CandleType::Day.get_end_date_of(&hour_start), CandleType::Day.get_end_date(src_date));
+    }
+
+    #[tokio::test]
+    async fn get_start_dates_sorted() {
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 7, 13).unwrap();
+        let to: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 2, 3, 1, 0).unwrap();
+
+        for candle_type in [CandleType::Minute, CandleType::FifteenMinutes, CandleType::Hour, CandleType::Day] {
+            let dates = candle_type.get_start_dates(from, to);
+
+            assert_eq!(dates.first(), Some(&candle_type.get_start_date(from)));
+            assert_eq!(dates.last(), Some(&candle_type.get_start_date(to)));
+            assert!(dates.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+    }
+
+    #[tokio::test]
+    async fn get_start_dates_reversed_range() {
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 3, 1, 0, 0, 0).unwrap();
+        let to: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+
+        for candle_type in CandleType::ALL.iter() {
+            assert_eq!(candle_type.get_start_dates(from, to), vec![candle_type.get_start_date(from)]);
+        }
+    }
+
+    #[tokio::test]
+    async fn get_start_dates_matches_stepping() {
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let to = from + Duration::days(30);
+        let candle_type = CandleType::Minute;
+
+        let mut reference_dates = HashSet::new();
+        let mut last_date = candle_type.get_start_date(from);
+        reference_dates.insert(last_date);
+
+        while last_date < to {
+            last_date = candle_type.get_start_date(last_date + candle_type.get_duration(last_date));
+            reference_dates.insert(last_date);
+        }
+
+        let dates = candle_type.get_start_dates(from, to);
+
+        assert_eq!(dates.len(), 30 * 24 * 60 + 1);
+        assert_eq!(dates.capacity(), dates.len());
+        assert_eq!(dates.iter().cloned().collect::<HashSet<DateTime<Utc>>>(), reference_dates);
+    }
+
     #[tokio::test]
     async fn get_start_dates_for_minute() {
         let duration = Duration::minutes(15);
@@ -393,12 +1250,30 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn get_start_dates_for_month_cross_year() {
+        let from: DateTime<Utc> = Utc.with_ymd_and_hms(2023, 11, 15, 0, 0, 0).unwrap();
+        let to: DateTime<Utc> = Utc.with_ymd_and_hms(2024, 2, 10, 0, 0, 0).unwrap();
+
+        let dates = CandleType::Monthly.get_start_dates(from, to);
+
+        assert_eq!(
+            dates,
+            vec![
+                Utc.with_ymd_and_hms(2023, 11, 1, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2023, 12, 1, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap(),
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn get_start_dates_for_month() {
         let num_months = 12;
         let from: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
         let to: DateTime<Utc> = Utc.with_ymd_and_hms(2000, num_months, 1, 0, 0, 0).unwrap();
-        let candle_type = CandleType::Month;
+        let candle_type = CandleType::Monthly;
 
         let dates = candle_type.get_start_dates(from, to);
         let dates: HashSet<DateTime<Utc>> = dates.into_iter().collect();
@@ -406,9 +1281,10 @@ mod tests {
         assert!(dates.contains(&candle_type.get_start_date(from)));
         assert!(dates.contains(&candle_type.get_start_date(to)));
         assert_eq!(dates.len(), num_months as usize);
+        assert!(dates.contains(&Utc.with_ymd_and_hms(2000, 12, 1, 0, 0, 0).unwrap()));
 
-        for _ in 0..num_months {
-            let date = candle_type.get_start_date(from) + candle_type.get_duration(from);
+        for month in 1..=num_months {
+            let date = Utc.with_ymd_and_hms(2000, month, 1, 0, 0, 0).unwrap();
             assert!(dates.contains(&date));
         }
     }
@@ -0,0 +1,33 @@
+use std::fmt;
+
+/// Violation of the candle price and volume invariants
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandleValidationError {
+    HighBelowOpen,
+    HighBelowClose,
+    LowAboveOpen,
+    LowAboveClose,
+    HighBelowLow,
+    NegativeVolume,
+    NonPositiveOpen,
+    NonFiniteValue,
+}
+
+impl fmt::Display for CandleValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            CandleValidationError::HighBelowOpen => "High is below open",
+            CandleValidationError::HighBelowClose => "High is below close",
+            CandleValidationError::LowAboveOpen => "Low is above open",
+            CandleValidationError::LowAboveClose => "Low is above close",
+            CandleValidationError::HighBelowLow => "High is below low",
+            CandleValidationError::NegativeVolume => "Volume is negative",
+            CandleValidationError::NonPositiveOpen => "Open is not positive",
+            CandleValidationError::NonFiniteValue => "Price or volume is not finite",
+        };
+
+        write!(f, "{}", message)
+    }
+}
+
+impl std::error::Error for CandleValidationError {}
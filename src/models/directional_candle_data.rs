@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+use super::candle_data::CandleData;
+
+/// Candle data with traded volume split by aggressor side
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectionalCandleData {
+    pub data: CandleData,
+    pub buy_volume: f64,
+    pub sell_volume: f64,
+}
+
+impl DirectionalCandleData {
+    pub fn new(datetime: DateTime<Utc>, price: f64, volume: f64, is_buy: bool) -> Self {
+        let (buy_volume, sell_volume) = if is_buy { (volume, 0.0) } else { (0.0, volume) };
+
+        Self {
+            data: CandleData::new(datetime, price, volume),
+            buy_volume,
+            sell_volume,
+        }
+    }
+
+    pub fn update_directional(&mut self, datetime: DateTime<Utc>, price: f64, volume: f64, is_buy: bool) {
+        self.data.update(datetime, price, volume);
+
+        if is_buy {
+            self.buy_volume += volume;
+        } else {
+            self.sell_volume += volume;
+        }
+    }
+
+    /// Buy volume minus sell volume
+    pub fn get_delta(&self) -> f64 {
+        self.buy_volume - self.sell_volume
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::directional_candle_data::DirectionalCandleData;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    #[tokio::test]
+    async fn update_directional() {
+        let datetime: DateTime<Utc> = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let mut candle = DirectionalCandleData::new(datetime, 100.0, 2.0, true);
+
+        candle.update_directional(datetime + Duration::seconds(1), 101.0, 3.0, false);
+        candle.update_directional(datetime + Duration::seconds(2), 99.0, 1.5, true);
+        candle.update_directional(datetime + Duration::seconds(3), 100.5, 4.0, false);
+
+        assert_eq!(candle.buy_volume, 3.5);
+        assert_eq!(candle.sell_volume, 7.0);
+        assert_eq!(candle.get_delta(), -3.5);
+        assert_eq!(candle.data.volume, 10.5);
+        assert_eq!(candle.data.high, 101.0);
+        assert_eq!(candle.data.low, 99.0);
+        assert_eq!(candle.data.close, 100.5);
+    }
+}
@@ -0,0 +1,8 @@
+/// Defines prices of candles created for missing periods
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GapFillMode {
+    /// Repeats close of the previous candle
+    Flat,
+    /// Interpolates linearly between closes of the surrounding candles
+    Linear,
+}
@@ -0,0 +1,41 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(std::io::Error),
+    /// Row has wrong column count or a value can't be parsed
+    MalformedRow { line: usize, message: String },
+    /// Row has no instrument
+    UnknownInstrument { line: usize },
+    /// Row candle type is invalid or not tracked by the cache
+    UnknownCandleType { line: usize, value: String },
+    InvalidDatetime { line: usize, value: String },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(error) => write!(f, "Failed to read csv: {}", error),
+            ImportError::MalformedRow { line, message } => {
+                write!(f, "Malformed row at line {}: {}", line, message)
+            }
+            ImportError::UnknownInstrument { line } => {
+                write!(f, "Unknown instrument at line {}", line)
+            }
+            ImportError::UnknownCandleType { line, value } => {
+                write!(f, "Unknown candle type {} at line {}", value, line)
+            }
+            ImportError::InvalidDatetime { line, value } => {
+                write!(f, "Invalid datetime {} at line {}", value, line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<std::io::Error> for ImportError {
+    fn from(error: std::io::Error) -> Self {
+        ImportError::Io(error)
+    }
+}
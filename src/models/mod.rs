@@ -1,4 +1,16 @@
 pub mod candle_type;
 pub mod candle_data;
 pub mod candle;
-pub mod candle_pager;
\ No newline at end of file
+pub mod candle_pager;
+pub mod directional_candle_data;
+pub mod candle_sdk_error;
+pub mod gap_fill_mode;
+pub mod import_error;
+pub mod exchange;
+pub mod candle_aligner;
+pub mod candle_start;
+pub mod candle_validation_error;
+pub mod open_policy;
+pub mod ohlcv;
+pub mod session;
+pub mod candle_price;
\ No newline at end of file
@@ -0,0 +1,27 @@
+use chrono::{DateTime, Utc};
+
+use super::candle_data::CandleData;
+
+/// Plain candle prices and volume for bulk export
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ohlcv {
+    pub datetime: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+impl From<&CandleData> for Ohlcv {
+    fn from(value: &CandleData) -> Self {
+        Self {
+            datetime: value.datetime,
+            open: value.open,
+            high: value.high,
+            low: value.low,
+            close: value.close,
+            volume: value.get_volume(),
+        }
+    }
+}
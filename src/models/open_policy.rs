@@ -0,0 +1,9 @@
+/// Defines open price of a newly created candle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OpenPolicy {
+    /// Uses price of the first tick
+    #[default]
+    FirstTick,
+    /// Uses close of the previous candle if it exists, so consecutive candles have no gaps
+    PriorClose,
+}
@@ -0,0 +1,28 @@
+/// Trading session of a candle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Session {
+    #[default]
+    Regular,
+    PreMarket,
+    AfterHours,
+}
+
+impl Session {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Session::Regular => "regular",
+            Session::PreMarket => "pre_market",
+            Session::AfterHours => "after_hours",
+        }
+    }
+
+    /// Parses session written by `as_str`
+    pub fn parse(value: &str) -> Option<Session> {
+        match value {
+            "regular" => Some(Session::Regular),
+            "pre_market" => Some(Session::PreMarket),
+            "after_hours" => Some(Session::AfterHours),
+            _ => None,
+        }
+    }
+}